@@ -0,0 +1,32 @@
+use grid_pathfinding::ascii::{self, AsciiMap, Overlay};
+use grid_pathfinding::SearchConfig;
+
+// In this example the grid, start and goal are read from an ASCII picture, where
+// - # marks an obstacle
+// - S marks the start
+// - G marks the goal
+// The found path is drawn on top of the grid along with the expanded nodes.
+
+fn main() {
+    let map: AsciiMap = "
+        S.........
+        .######...
+        ......#...
+        ..###.#...
+        ......#..G
+    "
+    .parse()
+    .unwrap();
+    let config = SearchConfig {
+        record_expanded: true,
+        ..SearchConfig::optimal()
+    };
+    let result = map
+        .grid
+        .get_path_result_single_goal(map.start.unwrap(), map.goals[0], &config)
+        .unwrap();
+    print!(
+        "{}",
+        ascii::render(&map.grid, &Overlay::from_result(&map.grid, &result))
+    );
+}
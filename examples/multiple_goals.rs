@@ -1,4 +1,4 @@
-use grid_pathfinding::PathingGrid;
+use grid_pathfinding::{PathingGrid, SearchConfig};
 use grid_util::grid::Grid;
 use grid_util::point::Point;
 
@@ -23,7 +23,9 @@ fn main() {
     let goal_1 = Point::new(2, 0);
     let goal_2 = Point::new(2, 2);
     let goals = vec![&goal_1, &goal_2];
-    let (selected_goal, path) = pathing_grid.get_path_multiple_goals(start, goals).unwrap();
+    let (selected_goal, path) = pathing_grid
+        .get_path_multiple_goals(start, goals, &SearchConfig::default())
+        .unwrap();
     println!("Selected goal: {:?}\n", selected_goal);
     println!("Path:");
     for p in path {
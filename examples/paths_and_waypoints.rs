@@ -1,4 +1,4 @@
-use grid_pathfinding::{waypoints_to_path, PathingGrid};
+use grid_pathfinding::{waypoints_to_path, PathingGrid, SearchConfig};
 use grid_util::grid::Grid;
 use grid_util::point::Point;
 
@@ -26,7 +26,8 @@ fn main() {
     println!("{}", pathing_grid);
     let start = Point::new(0, 0);
     let end = Point::new(4, 4);
-    if let Some(path) = pathing_grid.get_waypoints_single_goal(start, end, false) {
+    let config = SearchConfig::default();
+    if let Ok(path) = pathing_grid.get_waypoints_single_goal(start, end, &config) {
         println!("Waypoints:");
         for p in &path {
             println!("{:?}", p);
@@ -38,7 +39,7 @@ fn main() {
     }
     println!("\nDirectly computed path");
     let expanded_path = pathing_grid
-        .get_path_single_goal(start, end, false)
+        .get_path_single_goal(start, end, &config)
         .unwrap();
     for p in expanded_path {
         println!("{:?}", p);
@@ -1,4 +1,4 @@
-use grid_pathfinding::PathingGrid;
+use grid_pathfinding::{PathingGrid, SearchConfig};
 use grid_util::grid::Grid;
 use grid_util::point::Point;
 
@@ -21,7 +21,7 @@ fn main() {
     let start = Point::new(0, 0);
     let end = Point::new(2, 2);
     let path = pathing_grid
-        .get_path_single_goal(start, end, false)
+        .get_path_single_goal(start, end, &SearchConfig::default())
         .unwrap();
     println!("Path:");
     for p in path {
@@ -0,0 +1,277 @@
+//! Any-angle paths with [Theta*](https://arxiv.org/abs/1401.3843) on top of Jump Point Search.
+//! Jump points are generated as in JPS, but each successor is connected to the parent of the node
+//! it was generated from whenever there is a [line of sight](crate::line_of_sight) between them.
+//! Waypoints are therefore joined by straight lines at any angle instead of the 8 directions of
+//! the grid, and costs are Euclidean distances in the fixed point units of
+//! [OCTILE_STRAIGHT_COST].
+//!
+//! Like Theta*, the paths found are usually but not always the shortest any-angle paths. The
+//! waypoints can not be turned into grid paths with
+//! [waypoints_to_path](PathingGrid::waypoints_to_path), since the lines between them follow
+//! [line_steps](crate::line_of_sight::line_steps) instead.
+use std::collections::BinaryHeap;
+
+use grid_util::point::Point;
+use indexmap::map::Entry::{Occupied, Vacant};
+use itertools::Itertools;
+use log::info;
+
+use crate::astar_jps::{FxIndexMap, SmallestCostHolder};
+use crate::{
+    Connectivity, PathError, PathResult, PathingGrid, SearchConfig, SearchStats,
+    OCTILE_STRAIGHT_COST,
+};
+
+/// The Euclidean distance between two points in fixed point, rounded to the nearest integer.
+pub fn euclidean_cost(a: &Point, b: &Point) -> i32 {
+    let (dx, dy) = ((a.x - b.x) as f64, (a.y - b.y) as f64);
+    (dx.hypot(dy) * OCTILE_STRAIGHT_COST as f64).round() as i32
+}
+
+/// How a node was reached: its parent on the path, which it can see, and the jump point it was
+/// generated from, which determines the pruning of its successors.
+#[derive(Clone, Copy)]
+struct Node {
+    parent: usize,
+    jumped_from: usize,
+    cost: i32,
+    closed: bool,
+}
+
+impl PathingGrid {
+    /// Like [get_waypoints_single_goal](Self::get_waypoints_single_goal), but the waypoints are
+    /// joined by lines at any angle, see [any_angle](crate::any_angle).
+    pub fn get_waypoints_single_goal_any_angle(
+        &self,
+        start: Point,
+        goal: Point,
+        config: &SearchConfig,
+    ) -> Result<Vec<Point>, PathError> {
+        self.get_path_result_single_goal_any_angle(start, goal, config)
+            .map(|result| result.waypoints)
+    }
+    /// Like [get_path_result_single_goal](Self::get_path_result_single_goal), but the waypoints
+    /// are joined by lines at any angle and the cost is their total Euclidean length. Jump points
+    /// are generated without [improved pruning](SearchConfig::improved_pruning), so that every
+    /// successor lies on a straight line from the node it was generated from.
+    pub fn get_path_result_single_goal_any_angle(
+        &self,
+        start: Point,
+        goal: Point,
+        config: &SearchConfig,
+    ) -> Result<PathResult, PathError> {
+        self.check_start(&start)?;
+        let tolerance = config.goal_tolerance;
+        if self.region_unreachable(&start, &goal, tolerance) {
+            info!("{} is not reachable from {}", goal, start);
+            return Err(self.unreachable_error());
+        }
+        let reached = |node: &Point| self.move_distance(node, &goal) <= tolerance;
+        let target = (tolerance == 0).then_some(&goal);
+        let heuristic = |node: &Point| {
+            let distance = match self.connectivity {
+                Connectivity::Eight => {
+                    let closest = Point::new(
+                        node.x.clamp(goal.x - tolerance, goal.x + tolerance),
+                        node.y.clamp(goal.y - tolerance, goal.y + tolerance),
+                    );
+                    euclidean_cost(node, &closest)
+                }
+                // Points within the tolerance lie within this Euclidean distance of the goal.
+                Connectivity::Four => {
+                    (euclidean_cost(node, &goal) - tolerance * OCTILE_STRAIGHT_COST).max(0)
+                }
+            };
+            (distance as f64 * config.heuristic_weight as f64) as i32
+        };
+        let mut nodes: FxIndexMap<Point, Node> = FxIndexMap::default();
+        nodes.insert(
+            start,
+            Node {
+                parent: usize::MAX,
+                jumped_from: usize::MAX,
+                cost: 0,
+                closed: false,
+            },
+        );
+        let mut to_see = BinaryHeap::new();
+        to_see.push(SmallestCostHolder {
+            estimated_cost: 0,
+            cost: 0,
+            index: 0,
+            tie_breaking: config.tie_breaking,
+        });
+        let mut stats = SearchStats::default();
+        let mut jump_calls = 0;
+        let mut expanded = Vec::new();
+        let mut found = None;
+        while let Some(SmallestCostHolder { cost, index, .. }) = to_see.pop() {
+            let (&point, &node) = nodes.get_index(index).unwrap();
+            // Nodes may have been pushed several times, only their cheapest entry is expanded.
+            if node.closed || cost > node.cost {
+                continue;
+            }
+            if reached(&point) {
+                found = Some(index);
+                break;
+            }
+            if config
+                .max_expansions
+                .is_some_and(|max| stats.expanded >= max)
+            {
+                break;
+            }
+            nodes[index].closed = true;
+            stats.expanded += 1;
+            if config.record_expanded {
+                expanded.push(point);
+            }
+            let jumped_from = nodes.get_index(node.jumped_from).map(|(p, _)| *p);
+            let parent = nodes.get_index(node.parent).map(|(p, n)| (*p, n.cost));
+            let successors = self.jps_successors(
+                jumped_from.as_ref(),
+                &point,
+                &reached,
+                target,
+                false,
+                &mut jump_calls,
+            );
+            for (successor, _) in successors {
+                stats.generated += 1;
+                // Theta* skips the node if the successor can be seen from its parent.
+                let (parent_index, new_cost) = match parent {
+                    Some((p, c)) if self.line_of_sight(&p, &successor) => {
+                        (node.parent, c + euclidean_cost(&p, &successor))
+                    }
+                    _ => (index, cost + euclidean_cost(&point, &successor)),
+                };
+                let update = Node {
+                    parent: parent_index,
+                    jumped_from: index,
+                    cost: new_cost,
+                    closed: false,
+                };
+                let n = match nodes.entry(successor) {
+                    Vacant(e) => {
+                        let n = e.index();
+                        e.insert(update);
+                        n
+                    }
+                    Occupied(mut e) => {
+                        if !e.get().closed && e.get().cost > new_cost {
+                            e.insert(update);
+                            e.index()
+                        } else {
+                            continue;
+                        }
+                    }
+                };
+                to_see.push(SmallestCostHolder {
+                    estimated_cost: new_cost + heuristic(&successor),
+                    cost: new_cost,
+                    index: n,
+                    tie_breaking: config.tie_breaking,
+                });
+            }
+        }
+        stats.jump_calls = jump_calls;
+        let index = found.ok_or_else(|| self.search_failure(config, &stats))?;
+        let mut waypoints = itertools::unfold(index, |i| {
+            nodes.get_index(*i).map(|(point, node)| {
+                *i = node.parent;
+                *point
+            })
+        })
+        .collect_vec();
+        waypoints.reverse();
+        Ok(PathResult {
+            waypoints,
+            cost: nodes[index].cost,
+            goal,
+            stats,
+            expanded,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grid_util::grid::Grid;
+
+    use super::*;
+    use crate::{CostModel, DiagonalPolicy};
+
+    const MAP: &str = "
+        S.........#.........
+        ..........#.........
+        ..####....#....###..
+        ..#.......#......#..
+        ..#...#####......#..
+        ..#..............#..
+        ..######...#######..
+        .........#..........
+        .........#.........G
+    ";
+
+    /// Waypoints see each other, and the paths are no longer than those of JPS under the octile
+    /// costs, which overestimate Euclidean distances.
+    #[test]
+    fn finds_shorter_straight_paths() {
+        let map = crate::ascii::parse(MAP).unwrap();
+        let (start, goal) = (map.start.unwrap(), map.goals[0]);
+        for (connectivity, policy) in [
+            (Connectivity::Four, DiagonalPolicy::Always),
+            (Connectivity::Eight, DiagonalPolicy::Always),
+            (Connectivity::Eight, DiagonalPolicy::OneSideFree),
+            (Connectivity::Eight, DiagonalPolicy::BothSidesFree),
+        ] {
+            let mut pathing_grid = map.grid.clone();
+            pathing_grid.set_connectivity(connectivity);
+            pathing_grid.set_diagonal_policy(policy);
+            pathing_grid.set_cost_model(CostModel::Octile);
+            pathing_grid.update();
+            let config = SearchConfig::optimal();
+            let result = pathing_grid
+                .get_path_result_single_goal_any_angle(start, goal, &config)
+                .unwrap();
+            assert_eq!(result.waypoints.first(), Some(&start));
+            assert_eq!(result.waypoints.last(), Some(&goal));
+            let mut cost = 0;
+            for (a, b) in result.waypoints.iter().tuple_windows() {
+                assert!(pathing_grid.line_of_sight(a, b), "{} -> {}", a, b);
+                cost += euclidean_cost(a, b);
+            }
+            assert_eq!(cost, result.cost);
+            let octile = pathing_grid
+                .get_path_result_single_goal(start, goal, &config)
+                .unwrap();
+            assert!(result.cost < octile.cost, "{:?} {:?}", connectivity, policy);
+        }
+    }
+
+    #[test]
+    fn reports_errors() {
+        let mut pathing_grid = crate::ascii::parse(MAP).unwrap().grid;
+        let (start, goal) = (Point::new(0, 0), Point::new(19, 8));
+        let config = SearchConfig::optimal();
+        assert_eq!(
+            pathing_grid.get_waypoints_single_goal_any_angle(start, start, &config),
+            Ok(vec![start])
+        );
+        let budget = SearchConfig {
+            max_expansions: Some(2),
+            ..config
+        };
+        assert_eq!(
+            pathing_grid.get_waypoints_single_goal_any_angle(start, goal, &budget),
+            Err(PathError::BudgetExhausted)
+        );
+        for (x, y) in [(18, 8), (18, 7), (19, 7)] {
+            pathing_grid.set(x, y, true);
+        }
+        assert_eq!(
+            pathing_grid.get_waypoints_single_goal_any_angle(start, goal, &config),
+            Err(PathError::GoalUnreachable)
+        );
+    }
+}
@@ -0,0 +1,230 @@
+//! Conversion between a [PathingGrid] and ASCII pictures, so that maps can be written and searches
+//! inspected as readable text. Each line is a row, with the first line at `y = 0`, and each
+//! character a cell:
+//!
+//! - `#` a blocked cell
+//! - `.` a free cell
+//! - `S` the start, which is free
+//! - `G` a goal, which is free
+//!
+//! When [rendering](render), `*` marks the path, `o` its waypoints and `+` expanded nodes. These
+//! are parsed as free cells, so rendered pictures can be read back in.
+use core::fmt;
+use std::str::FromStr;
+
+use grid_util::grid::Grid;
+use grid_util::point::Point;
+
+use crate::{PathResult, PathingGrid};
+
+/// An error encountered while parsing an ASCII picture. Lines count from 1, including leading
+/// blank lines.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AsciiError {
+    /// The picture holds no rows.
+    Empty,
+    /// A row has a different length than the first row.
+    RowLength {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// A character does not correspond to a cell.
+    UnknownCell { line: usize, cell: char },
+    /// The picture has more than one start.
+    MultipleStarts { line: usize },
+}
+
+impl fmt::Display for AsciiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsciiError::Empty => write!(f, "picture is empty"),
+            AsciiError::RowLength {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: expected {} cells, found {}",
+                line, expected, found
+            ),
+            AsciiError::UnknownCell { line, cell } => {
+                write!(f, "line {}: unknown cell {:?}", line, cell)
+            }
+            AsciiError::MultipleStarts { line } => write!(f, "line {}: second start", line),
+        }
+    }
+}
+
+impl std::error::Error for AsciiError {}
+
+/// A grid parsed from an ASCII picture along with its marked start and goals.
+#[derive(Clone, Debug)]
+pub struct AsciiMap {
+    pub grid: PathingGrid,
+    pub start: Option<Point>,
+    /// The goals in reading order.
+    pub goals: Vec<Point>,
+}
+
+/// Parses an ASCII picture. Leading and trailing whitespace is ignored on every line, as are
+/// blank lines.
+pub fn parse(picture: &str) -> Result<AsciiMap, AsciiError> {
+    let rows = picture
+        .lines()
+        .enumerate()
+        .map(|(i, row)| (i + 1, row.trim()))
+        .filter(|(_, row)| !row.is_empty())
+        .collect::<Vec<_>>();
+    let width = match rows.first() {
+        Some((_, row)) => row.chars().count(),
+        None => return Err(AsciiError::Empty),
+    };
+    let mut blocked = Vec::with_capacity(width * rows.len());
+    let mut start = None;
+    let mut goals = Vec::new();
+    for (y, &(line, row)) in rows.iter().enumerate() {
+        let found = row.chars().count();
+        if found != width {
+            return Err(AsciiError::RowLength {
+                line,
+                expected: width,
+                found,
+            });
+        }
+        for (x, cell) in row.chars().enumerate() {
+            let point = Point::new(x as i32, y as i32);
+            match cell {
+                '#' | '.' | '*' | 'o' | '+' => {}
+                'S' if start.is_some() => return Err(AsciiError::MultipleStarts { line }),
+                'S' => start = Some(point),
+                'G' => goals.push(point),
+                _ => return Err(AsciiError::UnknownCell { line, cell }),
+            }
+            blocked.push(cell == '#');
+        }
+    }
+    let grid = PathingGrid::from_slice(width, rows.len(), &blocked);
+    Ok(AsciiMap { grid, start, goals })
+}
+
+impl FromStr for AsciiMap {
+    type Err = AsciiError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+impl FromStr for PathingGrid {
+    type Err = AsciiError;
+    /// Parses an ASCII picture, ignoring the start and goal markers.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s).map(|map| map.grid)
+    }
+}
+
+/// Points drawn on top of a grid by [render]. Where they overlap, the start and goals take
+/// precedence over waypoints, which take precedence over the path and then expanded nodes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Overlay {
+    pub start: Option<Point>,
+    pub goals: Vec<Point>,
+    pub path: Vec<Point>,
+    pub waypoints: Vec<Point>,
+    pub expanded: Vec<Point>,
+}
+
+impl Overlay {
+    /// Draws the outcome of a search, with the path between its waypoints.
+    pub fn from_result(grid: &PathingGrid, result: &PathResult) -> Overlay {
+        Overlay {
+            start: result.waypoints.first().copied(),
+            goals: vec![result.goal],
+            path: grid.waypoints_to_path(result.waypoints.clone()),
+            waypoints: result.waypoints.clone(),
+            expanded: result.expanded.clone(),
+        }
+    }
+}
+
+/// Draws the grid as an ASCII picture with the overlay on top, ending every row in a newline.
+pub fn render(grid: &PathingGrid, overlay: &Overlay) -> String {
+    let (w, h) = (grid.width(), grid.height());
+    let mut cells = (0..h)
+        .map(|y| {
+            (0..w)
+                .map(|x| if grid.get(x, y) { '#' } else { '.' })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut draw = |points: &[Point], c: char| {
+        for p in points {
+            if p.x >= 0 && p.y >= 0 && (p.x as usize) < w && (p.y as usize) < h {
+                cells[p.y as usize][p.x as usize] = c;
+            }
+        }
+    };
+    draw(&overlay.expanded, '+');
+    draw(&overlay.path, '*');
+    draw(&overlay.waypoints, 'o');
+    draw(&overlay.goals, 'G');
+    draw(overlay.start.as_slice(), 'S');
+    cells
+        .into_iter()
+        .map(|row| row.into_iter().chain(['\n']).collect::<String>())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SearchConfig;
+
+    #[test]
+    fn round_trip() {
+        let picture = "S.#\n..#\n#.G\n";
+        let map: AsciiMap = picture.parse().unwrap();
+        assert_eq!(map.start, Some(Point::new(0, 0)));
+        assert_eq!(map.goals, vec![Point::new(2, 2)]);
+        assert!(map.grid.get(2, 0) && map.grid.get(0, 2));
+        let overlay = Overlay {
+            start: map.start,
+            goals: map.goals.clone(),
+            ..Overlay::default()
+        };
+        assert_eq!(render(&map.grid, &overlay), picture);
+        assert_eq!(
+            "S.\n...".parse::<PathingGrid>().unwrap_err(),
+            AsciiError::RowLength {
+                line: 2,
+                expected: 2,
+                found: 3
+            }
+        );
+    }
+
+    #[test]
+    fn renders_path() {
+        let map = parse(
+            "
+            S.......
+            ........
+            .......G
+            ........
+            ",
+        )
+        .unwrap();
+        let result = map
+            .grid
+            .get_path_result_single_goal(map.start.unwrap(), map.goals[0], &SearchConfig::optimal())
+            .unwrap();
+        let rendered = render(&map.grid, &Overlay::from_result(&map.grid, &result));
+        assert_eq!(
+            rendered,
+            "S.......\n\
+             .o......\n\
+             ..*****G\n\
+             ........\n"
+        );
+    }
+}
@@ -11,12 +11,38 @@ use indexmap::map::Entry::{Occupied, Vacant};
 use num_traits::Zero;
 use rustc_hash::FxHasher;
 
-type FxIndexMap<K, V> = IndexMap<K, V, BuildHasherDefault<FxHasher>>;
+use crate::SearchConfig;
 
-struct SmallestCostHolder<K> {
-    estimated_cost: K,
-    cost: K,
-    index: usize,
+pub(crate) type FxIndexMap<K, V> = IndexMap<K, V, BuildHasherDefault<FxHasher>>;
+
+/// Determines which node is expanded first among nodes with the same estimated total cost.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TieBreaking {
+    /// Prefers the node with the highest cost so far, which is typically closest to the goal.
+    #[default]
+    HighestCost,
+    /// Prefers the node with the lowest cost so far, which explores more evenly.
+    LowestCost,
+}
+
+/// Statistics collected during a search.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchStats {
+    /// Number of nodes whose successors were generated.
+    pub expanded: usize,
+    /// Number of successors generated, including those which did not improve on known paths.
+    pub generated: usize,
+    /// Number of calls to the jump function of JPS, including recursive ones.
+    pub jump_calls: usize,
+}
+
+pub(crate) struct SmallestCostHolder<K> {
+    pub(crate) estimated_cost: K,
+    pub(crate) cost: K,
+    pub(crate) index: usize,
+    pub(crate) tie_breaking: TieBreaking,
 }
 
 impl<K: PartialEq> Eq for SmallestCostHolder<K> {}
@@ -36,17 +62,52 @@ impl<K: Ord> PartialOrd for SmallestCostHolder<K> {
 impl<K: Ord> Ord for SmallestCostHolder<K> {
     fn cmp(&self, other: &Self) -> Ordering {
         match other.estimated_cost.cmp(&self.estimated_cost) {
-            Ordering::Equal => self.cost.cmp(&other.cost),
+            Ordering::Equal => match self.tie_breaking {
+                TieBreaking::HighestCost => self.cost.cmp(&other.cost),
+                TieBreaking::LowestCost => other.cost.cmp(&self.cost),
+            },
             s => s,
         }
     }
 }
 
+/// Searches a path from start to a node for which success holds, with the default
+/// [SearchConfig]. The path runs backwards, from the node reached to start.
 pub fn astar_jps<N, C, FN, IN, FH, FS>(
+    start: &N,
+    successors: FN,
+    heuristic: FH,
+    success: FS,
+) -> Option<(impl Iterator<Item=N>, C)>
+    where
+        N: Eq + Hash + Clone,
+        C: Zero + Ord + Copy,
+        FN: FnMut(&Option<&N>, &N) -> IN,
+        IN: IntoIterator<Item=(N, C)>,
+        FH: FnMut(&N) -> C,
+        FS: FnMut(&N) -> bool,
+{
+    astar_jps_with_config(
+        start,
+        successors,
+        heuristic,
+        success,
+        &SearchConfig::default(),
+        &mut SearchStats::default(),
+    )
+}
+
+/// Like [astar_jps], but gives up after the [max_expansions](SearchConfig::max_expansions) of
+/// the config and breaks ties according to its [tie_breaking](SearchConfig::tie_breaking).
+/// The other settings are up to the successors and heuristic. Expanded and generated nodes are
+/// counted in stats.
+pub fn astar_jps_with_config<N, C, FN, IN, FH, FS>(
     start: &N,
     mut successors: FN,
     mut heuristic: FH,
     mut success: FS,
+    config: &SearchConfig,
+    stats: &mut SearchStats,
 ) -> Option<(impl Iterator<Item=N>, C)>
     where
         N: Eq + Hash + Clone,
@@ -56,12 +117,19 @@ pub fn astar_jps<N, C, FN, IN, FH, FS>(
         FH: FnMut(&N) -> C,
         FS: FnMut(&N) -> bool,
 {
+    let SearchConfig {
+        max_expansions,
+        tie_breaking,
+        ..
+    } = *config;
     let mut to_see = BinaryHeap::new();
     to_see.push(SmallestCostHolder {
         estimated_cost: Zero::zero(),
         cost: Zero::zero(),
         index: 0,
+        tie_breaking,
     });
+    let mut expansions = 0;
     let mut parents: FxIndexMap<N, (usize, C)> = FxIndexMap::default();
     parents.insert(start.clone(), (usize::MAX, Zero::zero()));
     while let Some(SmallestCostHolder { cost, index, .. }) = to_see.pop() {
@@ -82,10 +150,16 @@ pub fn astar_jps<N, C, FN, IN, FH, FS>(
             if cost > c {
                 continue;
             }
+            if max_expansions.is_some_and(|max| expansions >= max) {
+                return None;
+            }
+            expansions += 1;
+            stats.expanded += 1;
             let optional_parent_node = parents.get_index(parent_index).map(|x| x.0);
             successors(&optional_parent_node, node)
         };
         for (successor, move_cost) in successors {
+            stats.generated += 1;
             let new_cost = cost + move_cost;
             let h; // heuristic(&successor)
             let n; // index for successor
@@ -110,6 +184,7 @@ pub fn astar_jps<N, C, FN, IN, FH, FS>(
                 estimated_cost: new_cost + h,
                 cost: new_cost,
                 index: n,
+                tie_breaking,
             });
         }
     }
@@ -0,0 +1,373 @@
+//! Bidirectional Jump Point Search for single goal queries. One search runs forward from the start
+//! while another runs backward from the goal, which roughly halves the explored area on large open
+//! maps. Jumps of either search stop at nodes already reached by the other, so that the searches
+//! meet in the middle. Since moves on a [PathingGrid] can be reversed under every
+//! [DiagonalPolicy](crate::DiagonalPolicy), the backward search is a regular JPS towards the start.
+//!
+//! Searching stops once the cheapest meeting point found can no longer be improved upon, so with
+//! [SearchConfig::optimal] the paths are as short as those of
+//! [get_waypoints_single_goal](PathingGrid::get_waypoints_single_goal).
+use std::collections::BinaryHeap;
+
+use grid_util::point::Point;
+use indexmap::map::Entry::{Occupied, Vacant};
+use itertools::Itertools;
+use log::info;
+
+use crate::astar_jps::{FxIndexMap, SmallestCostHolder};
+use crate::{PathError, PathResult, PathingGrid, SearchConfig, SearchStats, TieBreaking};
+
+/// The state of the search in one direction.
+struct Frontier {
+    to_see: BinaryHeap<SmallestCostHolder<i32>>,
+    parents: FxIndexMap<Point, (usize, i32)>,
+    /// The point the search heads towards and the move distance within which it counts as reached.
+    target: Point,
+    radius: i32,
+    improved_pruning: bool,
+}
+
+impl Frontier {
+    fn new(
+        roots: &[Point],
+        target: Point,
+        radius: i32,
+        improved_pruning: bool,
+        tie_breaking: TieBreaking,
+    ) -> Frontier {
+        let mut frontier = Frontier {
+            to_see: BinaryHeap::new(),
+            parents: FxIndexMap::default(),
+            target,
+            radius,
+            improved_pruning,
+        };
+        for &root in roots {
+            let (index, _) = frontier.parents.insert_full(root, (usize::MAX, 0));
+            frontier.to_see.push(SmallestCostHolder {
+                estimated_cost: 0,
+                cost: 0,
+                index,
+                tie_breaking,
+            });
+        }
+        frontier
+    }
+    /// A lower bound on the cost of any path through a node which has not been expanded yet.
+    fn lower_bound(&self) -> Option<i32> {
+        self.to_see.peek().map(|holder| holder.estimated_cost)
+    }
+    /// The points from the root of the search to the node with the given index.
+    fn chain(&self, index: usize) -> Vec<Point> {
+        let mut chain = itertools::unfold(index, |i| {
+            self.parents.get_index(*i).map(|(node, (index, _))| {
+                *i = *index;
+                *node
+            })
+        })
+        .collect_vec();
+        chain.reverse();
+        chain
+    }
+}
+
+/// The cheapest meeting point found so far, with the indices of the point in both frontiers.
+#[derive(Clone, Copy)]
+struct Meeting {
+    cost: i32,
+    forward: usize,
+    backward: usize,
+}
+
+impl PathingGrid {
+    /// Like [get_waypoints_single_goal](Self::get_waypoints_single_goal), but searches from both
+    /// ends at once, see [bidirectional](crate::bidirectional).
+    pub fn get_waypoints_single_goal_bidirectional(
+        &self,
+        start: Point,
+        goal: Point,
+        config: &SearchConfig,
+    ) -> Result<Vec<Point>, PathError> {
+        self.get_path_result_single_goal_bidirectional(start, goal, config)
+            .map(|result| result.waypoints)
+    }
+    /// Like [get_path_result_single_goal](Self::get_path_result_single_goal), but searches from
+    /// both ends at once. The statistics and expanded nodes cover both searches.
+    pub fn get_path_result_single_goal_bidirectional(
+        &self,
+        start: Point,
+        goal: Point,
+        config: &SearchConfig,
+    ) -> Result<PathResult, PathError> {
+        self.check_start(&start)?;
+        let tolerance = config.goal_tolerance;
+        if self.region_unreachable(&start, &goal, tolerance) {
+            info!("{} is not reachable from {}", goal, start);
+            return Err(self.unreachable_error());
+        }
+        // The backward search starts from every free point which counts as reaching the goal.
+        let roots = (goal.x - tolerance..=goal.x + tolerance)
+            .cartesian_product(goal.y - tolerance..=goal.y + tolerance)
+            .map(|(x, y)| Point::new(x, y))
+            .filter(|p| self.move_distance(p, &goal) <= tolerance && self.can_move_to(*p))
+            .collect_vec();
+        let mut forward = Frontier::new(
+            &[start],
+            goal,
+            tolerance,
+            config.improved_pruning,
+            config.tie_breaking,
+        );
+        // Waypoints of the backward search are reversed, which only yields straight or diagonal
+        // lines between them without the immediate expansions of improved pruning.
+        let mut backward = Frontier::new(&roots, start, 0, false, config.tie_breaking);
+        let mut best = backward.parents.get_index_of(&start).map(|index| Meeting {
+            cost: 0,
+            forward: 0,
+            backward: index,
+        });
+        let mut stats = SearchStats::default();
+        let mut jump_calls = 0;
+        let mut expanded = Vec::new();
+        loop {
+            let bounds = (forward.lower_bound(), backward.lower_bound());
+            if let Some(meeting) = best {
+                let settled = |bound: Option<i32>| bound.map_or(true, |f| f >= meeting.cost);
+                if settled(bounds.0) || settled(bounds.1) {
+                    break;
+                }
+            }
+            if bounds.0.is_none() || bounds.1.is_none() {
+                break;
+            }
+            if config
+                .max_expansions
+                .is_some_and(|max| stats.expanded >= max)
+            {
+                best = None;
+                break;
+            }
+            // Expanding the smaller frontier keeps both searches balanced.
+            let from_start = forward.to_see.len() <= backward.to_see.len();
+            let (this, other) = if from_start {
+                (&mut forward, &backward)
+            } else {
+                (&mut backward, &forward)
+            };
+            self.expand_frontier(
+                this,
+                other,
+                from_start,
+                config,
+                &mut best,
+                &mut stats,
+                &mut jump_calls,
+                &mut expanded,
+            );
+        }
+        stats.jump_calls = jump_calls;
+        let meeting = best.ok_or_else(|| self.search_failure(config, &stats))?;
+        let mut waypoints = forward.chain(meeting.forward);
+        let mut rest = backward.chain(meeting.backward);
+        rest.reverse();
+        waypoints.extend(rest.into_iter().skip(1));
+        Ok(PathResult {
+            waypoints,
+            cost: meeting.cost,
+            goal,
+            stats,
+            expanded,
+        })
+    }
+    /// Expands the most promising node of a frontier, recording where it meets the other one.
+    #[allow(clippy::too_many_arguments)]
+    fn expand_frontier(
+        &self,
+        this: &mut Frontier,
+        other: &Frontier,
+        from_start: bool,
+        config: &SearchConfig,
+        best: &mut Option<Meeting>,
+        stats: &mut SearchStats,
+        jump_calls: &mut usize,
+        expanded: &mut Vec<Point>,
+    ) {
+        let SmallestCostHolder { cost, index, .. } = this.to_see.pop().unwrap();
+        let (&node, &(parent_index, c)) = this.parents.get_index(index).unwrap();
+        // Nodes may have been pushed several times, only their cheapest entry is expanded.
+        if cost > c {
+            return;
+        }
+        stats.expanded += 1;
+        if config.record_expanded {
+            expanded.push(node);
+        }
+        let parent = this.parents.get_index(parent_index).map(|(p, _)| *p);
+        let reached = |p: &Point| other.parents.contains_key(p);
+        let successors = self.jps_successors(
+            parent.as_ref(),
+            &node,
+            &reached,
+            None,
+            this.improved_pruning,
+            jump_calls,
+        );
+        for (successor, move_cost) in successors {
+            stats.generated += 1;
+            let new_cost = cost + move_cost;
+            let n = match this.parents.entry(successor) {
+                Vacant(e) => {
+                    let n = e.index();
+                    e.insert((index, new_cost));
+                    n
+                }
+                Occupied(mut e) => {
+                    if e.get().1 > new_cost {
+                        e.insert((index, new_cost));
+                        e.index()
+                    } else {
+                        continue;
+                    }
+                }
+            };
+            if let Some((m, _, &(_, other_cost))) = other.parents.get_full(&successor) {
+                let total = new_cost + other_cost;
+                if best.map_or(true, |meeting| total < meeting.cost) {
+                    *best = Some(if from_start {
+                        Meeting {
+                            cost: total,
+                            forward: n,
+                            backward: m,
+                        }
+                    } else {
+                        Meeting {
+                            cost: total,
+                            forward: m,
+                            backward: n,
+                        }
+                    });
+                }
+            }
+            let h = self.heuristic(
+                &successor,
+                &this.target,
+                this.radius,
+                config.heuristic_weight,
+            );
+            this.to_see.push(SmallestCostHolder {
+                estimated_cost: new_cost + h,
+                cost: new_cost,
+                index: n,
+                tie_breaking: config.tie_breaking,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grid_util::grid::Grid;
+
+    use super::*;
+    use crate::{Connectivity, CostModel, DiagonalPolicy};
+
+    const MAP: &str = "
+        S.........#.........
+        ..........#.........
+        ..####....#....###..
+        ..#.......#......#..
+        ..#...#####......#..
+        ..#..............#..
+        ..######...#######..
+        .........#..........
+        .........#.........G
+    ";
+
+    /// Bidirectional searches find paths of the same cost as forward searches, and their
+    /// waypoints can be followed on the grid.
+    #[test]
+    fn matches_forward_search() {
+        let map = crate::ascii::parse(MAP).unwrap();
+        let (start, goal) = (map.start.unwrap(), map.goals[0]);
+        for connectivity in [Connectivity::Eight, Connectivity::Four] {
+            for policy in [
+                DiagonalPolicy::Always,
+                DiagonalPolicy::OneSideFree,
+                DiagonalPolicy::BothSidesFree,
+            ] {
+                let mut pathing_grid = map.grid.clone();
+                pathing_grid.set_connectivity(connectivity);
+                pathing_grid.set_diagonal_policy(policy);
+                pathing_grid.set_cost_model(CostModel::Octile);
+                pathing_grid.update();
+                for tolerance in [0, 1] {
+                    let config = SearchConfig {
+                        goal_tolerance: tolerance,
+                        ..SearchConfig::optimal()
+                    };
+                    let forward = pathing_grid
+                        .get_path_result_single_goal(start, goal, &config)
+                        .unwrap();
+                    let both = pathing_grid
+                        .get_path_result_single_goal_bidirectional(start, goal, &config)
+                        .unwrap();
+                    assert_eq!(both.cost, forward.cost, "{:?} {:?}", connectivity, policy);
+                    assert_eq!(both.waypoints.first(), Some(&start));
+                    let end = *both.waypoints.last().unwrap();
+                    assert!(pathing_grid.move_distance(&end, &goal) <= tolerance);
+                    let path = pathing_grid.waypoints_to_path(both.waypoints);
+                    for (a, b) in path.iter().tuple_windows() {
+                        assert!(pathing_grid.can_step(a, a.dir_obj(b)), "{} -> {}", a, b);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reports_errors() {
+        let mut pathing_grid = crate::ascii::parse(MAP).unwrap().grid;
+        let (start, goal) = (Point::new(0, 0), Point::new(19, 8));
+        assert_eq!(
+            pathing_grid.get_waypoints_single_goal_bidirectional(
+                start,
+                start,
+                &SearchConfig::optimal()
+            ),
+            Ok(vec![start])
+        );
+        let budget = SearchConfig {
+            max_expansions: Some(2),
+            ..SearchConfig::optimal()
+        };
+        assert_eq!(
+            pathing_grid.get_waypoints_single_goal_bidirectional(start, goal, &budget),
+            Err(PathError::BudgetExhausted)
+        );
+        // Like the forward search, a budget short of settling the path never yields one.
+        let full = pathing_grid
+            .get_path_result_single_goal_bidirectional(start, goal, &SearchConfig::optimal())
+            .unwrap();
+        for max in 0..full.stats.expanded {
+            let budget = SearchConfig {
+                max_expansions: Some(max),
+                ..SearchConfig::optimal()
+            };
+            assert_eq!(
+                pathing_grid.get_waypoints_single_goal_bidirectional(start, goal, &budget),
+                Err(PathError::BudgetExhausted)
+            );
+        }
+        pathing_grid.set(18, 8, true);
+        pathing_grid.set(18, 7, true);
+        pathing_grid.set(19, 7, true);
+        assert_eq!(
+            pathing_grid.get_waypoints_single_goal_bidirectional(
+                start,
+                goal,
+                &SearchConfig::optimal()
+            ),
+            Err(PathError::GoalUnreachable)
+        );
+    }
+}
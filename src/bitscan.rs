@@ -0,0 +1,254 @@
+//! Straight jumps scanning 64 cells at once, following
+//! [JPS with bit operations](https://harablog.wordpress.com/2012/05/05/jump-point-search-bits-and-pieces/).
+//! The cells along a jump and along both of its sides are read as words of the bit-packed grid,
+//! in which the first blocked cell and the first cell with a forced neighbour are found by
+//! counting trailing zeros. Horizontal jumps read rows of the grid, while vertical jumps read rows
+//! of a transposed copy which is kept up to date as cells are set.
+//!
+//! Diagonal jumps, and vertical jumps on a 4-connected grid, still take single steps, but spend
+//! most of their time in the straight jumps they start at every step.
+use grid_util::direction::Direction;
+use grid_util::grid::{BoolGrid, Grid};
+use grid_util::point::Point;
+
+use crate::jps_plus::steps_between;
+use crate::{Connectivity, DiagonalPolicy, PathingGrid};
+
+/// Cells in a word.
+const WORD: i32 = 64;
+
+/// Builds the transpose of a grid, whose rows are the columns of the grid.
+pub(crate) fn transpose(grid: &BoolGrid) -> BoolGrid {
+    let mut transposed = BoolGrid::new(grid.height, grid.width, false);
+    for y in 0..grid.height {
+        for x in 0..grid.width {
+            if grid.get(x, y) {
+                transposed.set(y, x, true);
+            }
+        }
+    }
+    transposed
+}
+
+/// Reads up to 64 bits of the grid starting at the given bit index.
+fn read_bits(grid: &BoolGrid, ix: usize, len: i32) -> u64 {
+    let (word, offset) = (ix / 64, ix % 64);
+    let mut bits = grid.values[word] >> offset;
+    if offset > 0 && word + 1 < grid.values.len() {
+        bits |= grid.values[word + 1] << (64 - offset);
+    }
+    if len < WORD {
+        bits & ((1 << len) - 1)
+    } else {
+        bits
+    }
+}
+
+/// Reads the cells of a row of the grid as a word, in which bit `i` is set if the cell at
+/// `start + i * step` is blocked. Cells outside of the grid count as blocked.
+fn blocked_word(grid: &BoolGrid, row: i32, start: i32, step: i32) -> u64 {
+    if row < 0 || row >= grid.height as i32 {
+        return u64::MAX;
+    }
+    // Reading backwards reads the same cells forwards and reverses them.
+    let first = if step > 0 { start } else { start - (WORD - 1) };
+    let begin = first.max(0);
+    let end = (first + WORD).min(grid.width as i32);
+    let mut word = u64::MAX;
+    if begin < end {
+        let len = end - begin;
+        let bits = read_bits(grid, row as usize * grid.width + begin as usize, len);
+        let mask = if len < WORD { (1 << len) - 1 } else { u64::MAX };
+        let shift = begin - first;
+        word = (word & !(mask << shift)) | bits << shift;
+    }
+    if step > 0 {
+        word
+    } else {
+        word.reverse_bits()
+    }
+}
+
+/// Where a straight jump stops, in steps from its initial cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stop {
+    /// The cell at this many steps has a forced neighbour.
+    Forced(i32),
+    /// The cell after this many steps is blocked.
+    Wall(i32),
+}
+
+impl PathingGrid {
+    /// Whether jumps in the given direction only move along a line, without scanning in other
+    /// directions at every step.
+    pub(crate) fn jumps_straight(&self, dir: Direction) -> bool {
+        !dir.diagonal() && (self.connectivity == Connectivity::Eight || Self::horizontal(dir))
+    }
+    /// Scans along a line from initial in a straight direction until a wall or a cell with a
+    /// forced neighbour, 64 cells at a time.
+    fn scan_straight(&self, initial: &Point, dir: Direction) -> Stop {
+        let (grid, row, position, step) = if dir.y() == 0 {
+            (&self.grid, initial.y, initial.x, dir.x())
+        } else {
+            (&self.transposed, initial.x, initial.y, dir.y())
+        };
+        // A side opens up if it is free while the cell behind it is blocked, see opened_sides.
+        let opening = self.connectivity == Connectivity::Four
+            || self.diagonal_policy == DiagonalPolicy::BothSidesFree;
+        let mut start = position + step;
+        loop {
+            let blocked = blocked_word(grid, row, start, step);
+            let sides = [row - 1, row + 1].map(|side| {
+                let here = blocked_word(grid, side, start, step);
+                if opening {
+                    !here & blocked_word(grid, side, start - step, step)
+                } else {
+                    here
+                }
+            });
+            let forced = (sides[0] | sides[1]) & !blocked;
+            let (wall, first_forced) = (blocked.trailing_zeros(), forced.trailing_zeros());
+            let steps = (start - position) * step;
+            if first_forced < wall {
+                return Stop::Forced(steps + first_forced as i32);
+            }
+            if wall < 64 {
+                return Stop::Wall(steps + wall as i32 - 1);
+            }
+            start += WORD * step;
+        }
+    }
+    /// Jumps along a line, giving the same result as stepping through it with
+    /// [jump](Self::jump). A single target, which goal then only holds for, is located along the
+    /// line arithmetically. Otherwise only the cells before the stop found by scanning are checked
+    /// for the goal.
+    pub(crate) fn jump_straight<F>(
+        &self,
+        initial: &Point,
+        cost: i32,
+        direction: Direction,
+        goal: &F,
+        target: Option<&Point>,
+    ) -> Option<(Point, i32)>
+    where
+        F: Fn(&Point) -> bool,
+    {
+        let (steps, forced) = match self.scan_straight(initial, direction) {
+            Stop::Forced(steps) => (steps, true),
+            Stop::Wall(steps) => (steps, false),
+        };
+        let step_cost = self.step_cost(direction);
+        let cell = |steps: i32| *initial + Point::new(direction.x() * steps, direction.y() * steps);
+        let reached = match target {
+            Some(target) => steps_between(initial, direction, target).filter(|&s| s <= steps),
+            None => (1..=steps).find(|&steps| goal(&cell(steps))),
+        };
+        reached
+            .or(forced.then_some(steps))
+            .map(|steps| (cell(steps), cost + step_cost * (steps - 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A jump stepping through cells one at a time, as the jump of JPS does.
+    fn step_jump(
+        pathing_grid: &PathingGrid,
+        initial: &Point,
+        dir: Direction,
+        goal: &Point,
+    ) -> Option<(Point, i32)> {
+        let mut node = *initial;
+        let mut cost = 1;
+        while pathing_grid.can_step(&node, dir) {
+            node = node + dir;
+            if node == *goal || pathing_grid.is_forced(dir, &node) {
+                return Some((node, cost));
+            }
+            cost += 1;
+        }
+        None
+    }
+
+    /// Scanning jumps agree with stepping jumps on a grid wider and taller than a word, for every
+    /// free cell, straight direction and a selection of goals.
+    #[test]
+    fn matches_stepping_jumps() {
+        let (w, h) = (150, 70);
+        // Clear bands leave long runs along row 40 and column 20 which span several words.
+        let blocked = |x: usize, y: usize| {
+            ((x * 7 + y * 13) % 17 == 3 || (x * y) % 23 == 5)
+                && !(38..=42).contains(&y)
+                && !(18..=22).contains(&x)
+        };
+        for (connectivity, policy) in [
+            (Connectivity::Four, DiagonalPolicy::Always),
+            (Connectivity::Eight, DiagonalPolicy::Always),
+            (Connectivity::Eight, DiagonalPolicy::OneSideFree),
+            (Connectivity::Eight, DiagonalPolicy::BothSidesFree),
+        ] {
+            let mut pathing_grid = PathingGrid::from_fn(w, h, blocked);
+            pathing_grid.set_connectivity(connectivity);
+            pathing_grid.set_diagonal_policy(policy);
+            pathing_grid.set(100, 30, true);
+            pathing_grid.set(0, 0, false);
+            let goals = [Point::new(0, 0), Point::new(140, 3), Point::new(70, 69)];
+            for x in 0..w as i32 {
+                for y in 0..h as i32 {
+                    let initial = Point::new(x, y);
+                    if !pathing_grid.can_move_to(initial) {
+                        continue;
+                    }
+                    for dir in [
+                        Direction::NORTH,
+                        Direction::EAST,
+                        Direction::SOUTH,
+                        Direction::WEST,
+                    ] {
+                        if !pathing_grid.jumps_straight(dir) {
+                            continue;
+                        }
+                        for goal in &goals {
+                            let expected = step_jump(&pathing_grid, &initial, dir, goal);
+                            let reached = |p: &Point| p == goal;
+                            for target in [None, Some(goal)] {
+                                assert_eq!(
+                                    pathing_grid.jump_straight(&initial, 1, dir, &reached, target),
+                                    expected,
+                                    "{} {:?} {:?} {:?}",
+                                    initial,
+                                    dir,
+                                    connectivity,
+                                    policy
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// With a single target, a jump across open terrain never checks cells for the goal, while an
+    /// arbitrary goal is checked at every cell up to the stop.
+    #[test]
+    fn locates_target_without_checking_cells() {
+        let pathing_grid = PathingGrid::new(1000, 3, false);
+        let initial = Point::new(0, 1);
+        let target = Point::new(700, 1);
+        let checks = std::cell::Cell::new(0);
+        let reached = |p: &Point| {
+            checks.set(checks.get() + 1);
+            *p == target
+        };
+        let expected = Some((target, 700));
+        let jump = pathing_grid.jump_straight(&initial, 1, Direction::EAST, &reached, Some(&target));
+        assert_eq!(jump, expected);
+        assert_eq!(checks.get(), 0);
+        let jump = pathing_grid.jump_straight(&initial, 1, Direction::EAST, &reached, None);
+        assert_eq!(jump, expected);
+        assert_eq!(checks.get(), 700);
+    }
+}
@@ -0,0 +1,298 @@
+//! Connected components of the free cells of a grid, stored as a label per cell so that they can
+//! be maintained incrementally. Unblocking a cell merges the components around it by relabelling
+//! the smaller ones, while blocking a cell runs a breadth-first search from each of its neighbours
+//! in lockstep. The searches stop as soon as they meet or all but one of them run out, so only
+//! the cells of the parts that split off are visited.
+//!
+//! Cells are identified by their index in the grid and adjacency is provided by the caller, which
+//! keeps this module independent of the movement rules of the grid.
+use std::collections::VecDeque;
+
+use rustc_hash::FxHashMap;
+
+/// Label of cells which are not part of any component, i.e. blocked cells.
+pub const NO_COMPONENT: usize = usize::MAX;
+
+/// Labels every cell with the component it belongs to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Components {
+    labels: Vec<usize>,
+    /// Number of cells per label, zero for labels which are not in use.
+    sizes: Vec<usize>,
+    free_labels: Vec<usize>,
+}
+
+impl Components {
+    /// Creates components for a grid of the given number of cells which are all blocked.
+    pub fn new(len: usize) -> Components {
+        Components {
+            labels: vec![NO_COMPONENT; len],
+            sizes: Vec::new(),
+            free_labels: Vec::new(),
+        }
+    }
+    /// Creates components for a grid of the given number of cells which are all free and connected.
+    pub fn single(len: usize) -> Components {
+        if len == 0 {
+            return Components::new(0);
+        }
+        Components {
+            labels: vec![0; len],
+            sizes: vec![len],
+            free_labels: Vec::new(),
+        }
+    }
+    /// The label of the component a cell belongs to, or [NO_COMPONENT] if it is blocked.
+    pub fn label(&self, ix: usize) -> usize {
+        self.labels[ix]
+    }
+    /// The number of cells in the component with the given label.
+    pub fn size(&self, label: usize) -> usize {
+        self.sizes.get(label).copied().unwrap_or(0)
+    }
+    /// The number of components.
+    pub fn count(&self) -> usize {
+        self.sizes.len() - self.free_labels.len()
+    }
+    /// Checks whether two cells are free and belong to the same component.
+    pub fn equiv(&self, a: usize, b: usize) -> bool {
+        self.labels[a] != NO_COMPONENT && self.labels[a] == self.labels[b]
+    }
+    /// Rebuilds components from the label of every cell and the size of every label, as produced
+    /// by [labels](Self::labels) and [sizes](Self::sizes).
+    pub(crate) fn from_raw(labels: Vec<usize>, sizes: Vec<usize>) -> Components {
+        let free_labels = (0..sizes.len()).rev().filter(|&l| sizes[l] == 0).collect();
+        Components {
+            labels,
+            sizes,
+            free_labels,
+        }
+    }
+    pub(crate) fn labels(&self) -> &[usize] {
+        &self.labels
+    }
+    pub(crate) fn sizes(&self) -> &[usize] {
+        &self.sizes
+    }
+    fn new_label(&mut self) -> usize {
+        match self.free_labels.pop() {
+            Some(label) => label,
+            None => {
+                self.sizes.push(0);
+                self.sizes.len() - 1
+            }
+        }
+    }
+    fn free_label(&mut self, label: usize) {
+        self.sizes[label] = 0;
+        self.free_labels.push(label);
+    }
+    /// Assigns label to start and all cells reachable from it through cells which carry the same
+    /// label as start, returning the number of cells relabelled.
+    fn flood<F>(&mut self, start: usize, label: usize, neighbours: &mut F) -> usize
+    where
+        F: FnMut(usize) -> Vec<usize>,
+    {
+        let from = self.labels[start];
+        let mut queue = VecDeque::from([start]);
+        self.labels[start] = label;
+        let mut count = 1;
+        while let Some(ix) = queue.pop_front() {
+            for n in neighbours(ix) {
+                if self.labels[n] == from {
+                    self.labels[n] = label;
+                    count += 1;
+                    queue.push_back(n);
+                }
+            }
+        }
+        count
+    }
+    /// Labels all free cells from scratch. Cells for which free returns [false] are blocked.
+    pub fn generate<P, F>(&mut self, free: P, mut neighbours: F)
+    where
+        P: Fn(usize) -> bool,
+        F: FnMut(usize) -> Vec<usize>,
+    {
+        let len = self.labels.len();
+        *self = Components::new(len);
+        for ix in 0..len {
+            if free(ix) && self.labels[ix] == NO_COMPONENT {
+                let label = self.new_label();
+                self.sizes[label] = self.flood(ix, label, &mut neighbours);
+            }
+        }
+    }
+    /// Updates the components after cell ix became free. Its neighbours are given by neighbours,
+    /// which must already reflect the change. If several cells became free, this is called for
+    /// each of them in turn.
+    pub fn unblock<F>(&mut self, ix: usize, mut neighbours: F)
+    where
+        F: FnMut(usize) -> Vec<usize>,
+    {
+        if self.labels[ix] != NO_COMPONENT {
+            return;
+        }
+        let adjacent = neighbours(ix);
+        let mut labels = adjacent
+            .iter()
+            .map(|&n| self.labels[n])
+            .filter(|&label| label != NO_COMPONENT)
+            .collect::<Vec<_>>();
+        labels.sort_unstable();
+        labels.dedup();
+        // The largest component keeps its label and absorbs the others.
+        let keep = match labels.iter().max_by_key(|&&label| self.sizes[label]) {
+            Some(&label) => label,
+            None => self.new_label(),
+        };
+        self.labels[ix] = keep;
+        self.sizes[keep] += 1;
+        for n in adjacent {
+            let label = self.labels[n];
+            if label != keep && label != NO_COMPONENT {
+                self.sizes[keep] += self.flood(n, keep, &mut neighbours);
+                self.free_label(label);
+            }
+        }
+    }
+    /// Updates the components after cell ix became blocked, see [block_many](Self::block_many).
+    pub fn block<F>(&mut self, ix: usize, seeds: &[usize], neighbours: F)
+    where
+        F: FnMut(usize) -> Vec<usize>,
+    {
+        self.block_many(&[ix], seeds, neighbours);
+    }
+    /// Updates the components after the given cells became blocked. The seeds must include every
+    /// free cell next to a blocked cell, while neighbours must already reflect the change.
+    pub fn block_many<F>(&mut self, cells: &[usize], seeds: &[usize], mut neighbours: F)
+    where
+        F: FnMut(usize) -> Vec<usize>,
+    {
+        let mut affected = Vec::new();
+        for &ix in cells {
+            let label = self.labels[ix];
+            if label == NO_COMPONENT {
+                continue;
+            }
+            self.labels[ix] = NO_COMPONENT;
+            self.sizes[label] -= 1;
+            if self.sizes[label] == 0 {
+                self.free_label(label);
+            } else {
+                affected.push(label);
+            }
+        }
+        affected.sort_unstable();
+        affected.dedup();
+        affected.retain(|&label| self.sizes[label] > 0);
+        for label in affected {
+            let mut label_seeds = seeds
+                .iter()
+                .copied()
+                .filter(|&n| self.labels[n] == label)
+                .collect::<Vec<_>>();
+            label_seeds.sort_unstable();
+            label_seeds.dedup();
+            self.split(label, &label_seeds, &mut neighbours);
+        }
+    }
+    /// Splits off the parts of the component with the given label that are no longer connected,
+    /// by searching from the seeds in lockstep.
+    fn split<F>(&mut self, label: usize, seeds: &[usize], neighbours: &mut F)
+    where
+        F: FnMut(usize) -> Vec<usize>,
+    {
+        if seeds.len() < 2 {
+            return;
+        }
+        let mut owner = FxHashMap::default();
+        let mut queues = Vec::with_capacity(seeds.len());
+        let mut visited = Vec::with_capacity(seeds.len());
+        for (i, &seed) in seeds.iter().enumerate() {
+            owner.insert(seed, i);
+            queues.push(VecDeque::from([seed]));
+            visited.push(vec![seed]);
+        }
+        // Searches which met are joined into groups. For the root search of every group, the
+        // number of searches in it which have not run out yet is tracked.
+        let mut group = (0..seeds.len()).collect::<Vec<_>>();
+        let mut open_searches = vec![1; seeds.len()];
+        let mut groups = seeds.len();
+        let mut open_groups = seeds.len();
+        let root = |group: &mut Vec<usize>, mut i: usize| {
+            while group[i] != i {
+                group[i] = group[group[i]];
+                i = group[i];
+            }
+            i
+        };
+        'search: loop {
+            for i in 0..seeds.len() {
+                let Some(cell) = queues[i].pop_front() else {
+                    continue;
+                };
+                for n in neighbours(cell) {
+                    match owner.get(&n) {
+                        Some(&j) => {
+                            let (a, b) = (root(&mut group, i), root(&mut group, j));
+                            if a != b {
+                                group[b] = a;
+                                groups -= 1;
+                                if open_searches[a] > 0 && open_searches[b] > 0 {
+                                    open_groups -= 1;
+                                }
+                                open_searches[a] += open_searches[b];
+                            }
+                        }
+                        None => {
+                            owner.insert(n, i);
+                            queues[i].push_back(n);
+                            visited[i].push(n);
+                        }
+                    }
+                }
+                if queues[i].is_empty() {
+                    let r = root(&mut group, i);
+                    open_searches[r] -= 1;
+                    if open_searches[r] == 0 {
+                        open_groups -= 1;
+                    }
+                }
+                if groups == 1 {
+                    return;
+                }
+                if open_groups <= 1 {
+                    break 'search;
+                }
+            }
+        }
+        // All groups which ran out are complete components. The group which is still open, or
+        // else the largest group, keeps the original label.
+        let roots = (0..seeds.len())
+            .map(|i| root(&mut group, i))
+            .collect::<Vec<_>>();
+        let mut group_sizes = vec![0; seeds.len()];
+        for i in 0..seeds.len() {
+            group_sizes[roots[i]] += visited[i].len();
+        }
+        let keep = (0..seeds.len())
+            .filter(|&r| roots[r] == r)
+            .max_by_key(|&r| (open_searches[r] > 0, group_sizes[r]))
+            .unwrap();
+        let mut new_labels = FxHashMap::default();
+        for i in 0..seeds.len() {
+            if roots[i] == keep {
+                continue;
+            }
+            let new_label = *new_labels
+                .entry(roots[i])
+                .or_insert_with(|| self.new_label());
+            for &cell in &visited[i] {
+                self.labels[cell] = new_label;
+            }
+            self.sizes[new_label] += visited[i].len();
+            self.sizes[label] -= visited[i].len();
+        }
+    }
+}
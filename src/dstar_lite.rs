@@ -0,0 +1,370 @@
+//! Incremental replanning with [D* Lite](http://idm-lab.org/bib/abstracts/papers/aaai02b.pdf).
+//! A [DStarLite] planner searches backward from the goal over the neighbourhood of a
+//! [PathingGrid] and keeps its search state between calls. When cells change, only the costs
+//! around them are revisited, and as the agent advances its start moves along without discarding
+//! what was already found. Paths are of minimum cost under the [CostModel](crate::CostModel).
+//!
+//! The planner does not borrow the grid, so that the grid can be changed between calls. It must
+//! always be passed the same grid, and be told of every changed cell through
+//! [update_cells](DStarLite::update_cells).
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use grid_util::grid::Grid;
+use grid_util::point::Point;
+use log::info;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{PathError, PathingGrid, SearchConfig, SearchStats};
+
+/// Cost of points which have not been reached.
+const INFINITY: i32 = i32::MAX;
+
+/// Priority of a point in the queue, compared lexicographically.
+type Key = (i32, i32);
+
+/// A D* Lite planner from a start, which can move, to a fixed goal.
+#[derive(Clone, Debug)]
+pub struct DStarLite {
+    start: Point,
+    goal: Point,
+    /// The start when cells last changed, and the sum of the heuristic between successive such
+    /// starts which keeps the keys of queued points valid as the start moves.
+    last: Point,
+    key_modifier: i32,
+    /// Costs of the cheapest paths to the goal found so far, and those expected from the
+    /// neighbours of each point. Points are consistent once both agree.
+    g: FxHashMap<Point, i32>,
+    rhs: FxHashMap<Point, i32>,
+    /// Inconsistent points with their current keys. Queue entries whose key differs are outdated.
+    open: FxHashMap<Point, Key>,
+    queue: BinaryHeap<Reverse<(Key, i32, i32)>>,
+    stats: SearchStats,
+}
+
+impl DStarLite {
+    /// Sets up a planner from start to goal. No search happens until a path is requested.
+    pub fn new(pathing_grid: &PathingGrid, start: Point, goal: Point) -> DStarLite {
+        let mut planner = DStarLite {
+            start,
+            goal,
+            last: start,
+            key_modifier: 0,
+            g: FxHashMap::default(),
+            rhs: FxHashMap::default(),
+            open: FxHashMap::default(),
+            queue: BinaryHeap::new(),
+            stats: SearchStats::default(),
+        };
+        planner.rhs.insert(goal, 0);
+        planner.update_vertex(pathing_grid, goal);
+        planner
+    }
+    /// The point paths are planned from.
+    pub fn start(&self) -> Point {
+        self.start
+    }
+    /// The point paths are planned to.
+    pub fn goal(&self) -> Point {
+        self.goal
+    }
+    /// Statistics of the last call to [get_path](Self::get_path).
+    pub fn stats(&self) -> SearchStats {
+        self.stats
+    }
+    /// Moves the start, typically to the next point on the path as the agent advances.
+    pub fn move_start(&mut self, start: Point) {
+        self.start = start;
+    }
+    /// Repairs the search state after the given cells of the grid have changed.
+    pub fn update_cells(&mut self, pathing_grid: &PathingGrid, changed: &[Point]) {
+        self.key_modifier += pathing_grid.cost_distance(&self.last, &self.start);
+        self.last = self.start;
+        // Changing a cell changes the moves onto it and the diagonal moves past it, all of which
+        // start at the cell or one of its neighbours.
+        let affected = changed
+            .iter()
+            .flat_map(|p| std::iter::once(*p).chain(p.moore_neighborhood()))
+            .filter(|p| pathing_grid.point_in_bounds(*p))
+            .collect::<FxHashSet<_>>();
+        for point in affected {
+            if point != self.goal {
+                let rhs = self.best_rhs(pathing_grid, &point);
+                self.rhs.insert(point, rhs);
+            }
+            self.update_vertex(pathing_grid, point);
+        }
+    }
+    /// Computes a minimum cost path from the current start to the goal, reusing the search state
+    /// from previous calls.
+    pub fn get_path(&mut self, pathing_grid: &PathingGrid) -> Result<Vec<Point>, PathError> {
+        self.get_path_with_config(pathing_grid, &SearchConfig::default())
+    }
+    /// Like [get_path](Self::get_path), but gives up with [PathError::BudgetExhausted] after the
+    /// [max_expansions](SearchConfig::max_expansions) of the config. The search state is kept, so
+    /// a later call carries on where this one stopped. The other settings do not apply, as paths
+    /// are always of minimum cost.
+    pub fn get_path_with_config(
+        &mut self,
+        pathing_grid: &PathingGrid,
+        config: &SearchConfig,
+    ) -> Result<Vec<Point>, PathError> {
+        pathing_grid.check_start(&self.start)?;
+        if pathing_grid.unreachable(&self.start, &self.goal) {
+            info!("{} is not reachable from {}", self.goal, self.start);
+            return Err(pathing_grid.unreachable_error());
+        }
+        self.compute_shortest_path(pathing_grid, config.max_expansions)?;
+        if self.g(&self.start) == INFINITY {
+            return Err(pathing_grid.unreachable_error());
+        }
+        // Following the cheapest neighbours strictly decreases the cost to the goal.
+        let mut path = vec![self.start];
+        let mut node = self.start;
+        while node != self.goal {
+            let next = pathing_grid
+                .pathfinding_neighborhood(&node)
+                .into_iter()
+                .min_by_key(|(p, cost)| cost.saturating_add(self.g(p)))
+                .map(|(p, _)| p)
+                .filter(|p| self.g(p) < self.g(&node))
+                .ok_or_else(|| pathing_grid.unreachable_error())?;
+            path.push(next);
+            node = next;
+        }
+        Ok(path)
+    }
+    /// The cost of the path from the current start found by the last call to
+    /// [get_path](Self::get_path).
+    pub fn cost(&self) -> Option<i32> {
+        Some(self.g(&self.start)).filter(|&g| g < INFINITY)
+    }
+    fn g(&self, point: &Point) -> i32 {
+        self.g.get(point).copied().unwrap_or(INFINITY)
+    }
+    fn rhs(&self, point: &Point) -> i32 {
+        self.rhs.get(point).copied().unwrap_or(INFINITY)
+    }
+    fn key(&self, pathing_grid: &PathingGrid, point: &Point) -> Key {
+        let cost = self.g(point).min(self.rhs(point));
+        let h = pathing_grid.cost_distance(&self.start, point);
+        (
+            cost.saturating_add(h).saturating_add(self.key_modifier),
+            cost,
+        )
+    }
+    /// The cost of the cheapest path to the goal through a neighbour of point.
+    fn best_rhs(&self, pathing_grid: &PathingGrid, point: &Point) -> i32 {
+        if !pathing_grid.can_move_to(*point) {
+            return INFINITY;
+        }
+        pathing_grid
+            .pathfinding_neighborhood(point)
+            .into_iter()
+            .map(|(p, cost)| cost.saturating_add(self.g(&p)))
+            .min()
+            .unwrap_or(INFINITY)
+    }
+    /// Queues the point if it is inconsistent, and removes it from the queue otherwise.
+    fn update_vertex(&mut self, pathing_grid: &PathingGrid, point: Point) {
+        if self.g(&point) != self.rhs(&point) {
+            let key = self.key(pathing_grid, &point);
+            self.open.insert(point, key);
+            self.queue.push(Reverse((key, point.x, point.y)));
+        } else {
+            self.open.remove(&point);
+        }
+    }
+    /// The queued point with the smallest key, dropping outdated entries.
+    fn top(&mut self) -> Option<(Key, Point)> {
+        while let Some(&Reverse((key, x, y))) = self.queue.peek() {
+            let point = Point::new(x, y);
+            if self.open.get(&point) == Some(&key) {
+                return Some((key, point));
+            }
+            self.queue.pop();
+        }
+        None
+    }
+    /// Expands inconsistent points until the cost from the start is known, or the budget of
+    /// expansions runs out.
+    fn compute_shortest_path(
+        &mut self,
+        pathing_grid: &PathingGrid,
+        max_expansions: Option<usize>,
+    ) -> Result<(), PathError> {
+        self.stats = SearchStats::default();
+        while let Some((old_key, node)) = self.top() {
+            let start = self.start;
+            if old_key >= self.key(pathing_grid, &start) && self.rhs(&start) == self.g(&start) {
+                break;
+            }
+            let new_key = self.key(pathing_grid, &node);
+            if old_key < new_key {
+                self.open.insert(node, new_key);
+                self.queue.push(Reverse((new_key, node.x, node.y)));
+                continue;
+            }
+            if max_expansions.is_some_and(|max| self.stats.expanded >= max) {
+                return Err(PathError::BudgetExhausted);
+            }
+            self.queue.pop();
+            self.open.remove(&node);
+            self.stats.expanded += 1;
+            let neighbours = pathing_grid.pathfinding_neighborhood(&node);
+            self.stats.generated += neighbours.len();
+            let (g, rhs) = (self.g(&node), self.rhs(&node));
+            if g > rhs {
+                self.g.insert(node, rhs);
+                for (p, cost) in neighbours {
+                    if p != self.goal && cost.saturating_add(rhs) < self.rhs(&p) {
+                        self.rhs.insert(p, cost + rhs);
+                    }
+                    self.update_vertex(pathing_grid, p);
+                }
+            } else {
+                self.g.insert(node, INFINITY);
+                // Points whose cost came through node need to look for another way.
+                for (p, cost) in neighbours.into_iter().chain([(node, 0)]) {
+                    if p != self.goal && (p == node || self.rhs(&p) == cost.saturating_add(g)) {
+                        let rhs = self.best_rhs(pathing_grid, &p);
+                        self.rhs.insert(p, rhs);
+                    }
+                    self.update_vertex(pathing_grid, p);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::{Connectivity, CostModel, DiagonalPolicy};
+
+    const MAP: &str = "
+        S.........#.........
+        ..........#.........
+        ..####....#....###..
+        ..#.......#......#..
+        ..#...#####......#..
+        ..#..............#..
+        ..######...#######..
+        .........#..........
+        .........#.........G
+    ";
+
+    fn path_cost(pathing_grid: &PathingGrid, path: &[Point]) -> i32 {
+        path.iter()
+            .tuple_windows()
+            .map(|(a, b)| {
+                assert!(pathing_grid.can_step(a, a.dir_obj(b)), "{} -> {}", a, b);
+                pathing_grid.step_cost(a.dir_obj(b))
+            })
+            .sum()
+    }
+
+    fn optimal_cost(pathing_grid: &PathingGrid, start: Point, goal: Point) -> i32 {
+        pathing_grid
+            .get_path_result_single_goal(start, goal, &SearchConfig::optimal())
+            .unwrap()
+            .cost
+    }
+
+    /// Paths are as short as those found by JPS.
+    #[test]
+    fn matches_jps_costs() {
+        let map = crate::ascii::parse(MAP).unwrap();
+        let (start, goal) = (map.start.unwrap(), map.goals[0]);
+        for (connectivity, policy) in [
+            (Connectivity::Four, DiagonalPolicy::Always),
+            (Connectivity::Eight, DiagonalPolicy::Always),
+            (Connectivity::Eight, DiagonalPolicy::OneSideFree),
+            (Connectivity::Eight, DiagonalPolicy::BothSidesFree),
+        ] {
+            let mut pathing_grid = map.grid.clone();
+            pathing_grid.set_connectivity(connectivity);
+            pathing_grid.set_diagonal_policy(policy);
+            pathing_grid.set_cost_model(CostModel::Octile);
+            pathing_grid.update();
+            let mut planner = DStarLite::new(&pathing_grid, start, goal);
+            let path = planner.get_path(&pathing_grid).unwrap();
+            assert_eq!((path[0], *path.last().unwrap()), (start, goal));
+            let cost = path_cost(&pathing_grid, &path);
+            assert_eq!(Some(cost), planner.cost());
+            assert_eq!(cost, optimal_cost(&pathing_grid, start, goal));
+        }
+    }
+
+    /// After moving along the path and changing cells, the repaired paths are as short as fresh
+    /// ones while expanding fewer points overall.
+    #[test]
+    fn repairs_after_changes() {
+        let map = crate::ascii::parse(MAP).unwrap();
+        let mut pathing_grid = map.grid;
+        pathing_grid.set_cost_model(CostModel::Octile);
+        let goal = map.goals[0];
+        let mut planner = DStarLite::new(&pathing_grid, map.start.unwrap(), goal);
+        let mut path = planner.get_path(&pathing_grid).unwrap();
+        let mut expanded = (0, 0);
+        for (blocked, cells) in [
+            (
+                true,
+                vec![Point::new(10, 7), Point::new(11, 7), Point::new(12, 7)],
+            ),
+            (false, vec![Point::new(10, 3), Point::new(10, 4)]),
+            (true, vec![Point::new(18, 8), Point::new(18, 7)]),
+        ] {
+            planner.move_start(path[3]);
+            for p in &cells {
+                pathing_grid.set(p.x as usize, p.y as usize, blocked);
+            }
+            planner.update_cells(&pathing_grid, &cells);
+            path = planner.get_path(&pathing_grid).unwrap();
+            let mut fresh = DStarLite::new(&pathing_grid, planner.start(), goal);
+            fresh.get_path(&pathing_grid).unwrap();
+            let cost = path_cost(&pathing_grid, &path);
+            assert_eq!(cost, optimal_cost(&pathing_grid, planner.start(), goal));
+            assert_eq!(Some(cost), fresh.cost());
+            expanded.0 += planner.stats().expanded;
+            expanded.1 += fresh.stats().expanded;
+        }
+        assert!(expanded.0 < expanded.1, "{:?}", expanded);
+        let walls = [Point::new(19, 7), Point::new(18, 7)];
+        for p in walls {
+            pathing_grid.set(p.x as usize, p.y as usize, true);
+        }
+        planner.update_cells(&pathing_grid, &walls);
+        assert_eq!(
+            planner.get_path(&pathing_grid),
+            Err(PathError::GoalUnreachable)
+        );
+    }
+
+    /// A planner out of budget reports it, and finds the same path once given enough of it.
+    #[test]
+    fn reports_exhausted_budget() {
+        let map = crate::ascii::parse(MAP).unwrap();
+        let (start, goal) = (map.start.unwrap(), map.goals[0]);
+        let budget = SearchConfig {
+            max_expansions: Some(2),
+            ..SearchConfig::default()
+        };
+        let mut planner = DStarLite::new(&map.grid, start, goal);
+        assert_eq!(
+            planner.get_path_with_config(&map.grid, &budget),
+            Err(PathError::BudgetExhausted)
+        );
+        assert_eq!(planner.stats().expanded, 2);
+        assert_eq!(planner.cost(), None);
+        let path = planner.get_path(&map.grid).unwrap();
+        assert_eq!(
+            path_cost(&map.grid, &path),
+            optimal_cost(&map.grid, start, goal)
+        );
+        assert_eq!(planner.get_path_with_config(&map.grid, &budget), Ok(path));
+    }
+}
@@ -0,0 +1,165 @@
+//! Field of view using [symmetric shadowcasting](https://www.albertford.com/shadowcasting/). The
+//! area around a point is scanned row by row in each of the four quadrants, narrowing the visible
+//! range of slopes at every blocked cell. Visibility is symmetric: a free cell can see another free
+//! cell if and only if it is seen from it. Blocked cells are visible when light reaches them, so
+//! that walls around a room show up. Slopes are kept as exact fractions.
+use grid_util::grid::{BoolGrid, Grid};
+use grid_util::point::Point;
+
+use crate::PathingGrid;
+
+/// A row of cells at some depth from the origin, between two slopes given as fractions of a
+/// numerator and a positive denominator.
+#[derive(Clone, Copy)]
+struct Row {
+    depth: i32,
+    start: (i32, i32),
+    end: (i32, i32),
+}
+
+impl Row {
+    /// The first and last column of the row, rounding the start up and the end down when the
+    /// slopes hit the middle of an edge.
+    fn columns(&self) -> (i32, i32) {
+        let ((sn, sd), (en, ed)) = (self.start, self.end);
+        let first = (2 * self.depth * sn + sd).div_euclid(2 * sd);
+        let last = -(ed - 2 * self.depth * en).div_euclid(2 * ed);
+        (first, last)
+    }
+    /// Whether a cell lies within the slopes of the row by its centre, so that it is seen.
+    fn is_symmetric(&self, col: i32) -> bool {
+        let ((sn, sd), (en, ed)) = (self.start, self.end);
+        col * sd >= self.depth * sn && col * ed <= self.depth * en
+    }
+    fn next(&self) -> Row {
+        Row {
+            depth: self.depth + 1,
+            ..*self
+        }
+    }
+}
+
+/// The slope to the near edge of the cell at the given depth and column.
+fn slope(depth: i32, col: i32) -> (i32, i32) {
+    (2 * col - 1, 2 * depth)
+}
+
+impl PathingGrid {
+    /// Computes the cells visible from origin within the given Euclidean radius, measured between
+    /// cell centres, as a grid of the same size in which visible cells are set. The origin is
+    /// always visible, and cells outside of the grid block the view. Returns an empty field of view
+    /// if origin lies outside of the grid.
+    pub fn field_of_view(&self, origin: Point, radius: i32) -> BoolGrid {
+        let mut visible = BoolGrid::new(self.width(), self.height(), false);
+        if !self.point_in_bounds(origin) {
+            return visible;
+        }
+        visible.set_point(origin, true);
+        // Every cell of the grid lies within this radius, so larger ones see the same cells.
+        let radius = radius.min((self.width() + self.height()).min(i32::MAX as usize) as i32);
+        // Maps depth and column in each quadrant to a point on the grid.
+        let quadrants: [fn(i32, i32) -> Point; 4] = [
+            |depth, col| Point::new(col, depth),
+            |depth, col| Point::new(depth, col),
+            |depth, col| Point::new(col, -depth),
+            |depth, col| Point::new(-depth, col),
+        ];
+        for transform in quadrants {
+            let cell = |depth, col| origin + transform(depth, col);
+            // Rows are scanned from a stack rather than recursively, the result does not depend
+            // on their order.
+            let mut rows = vec![Row {
+                depth: 1,
+                start: (-1, 1),
+                end: (1, 1),
+            }];
+            while let Some(mut row) = rows.pop() {
+                if row.depth > radius {
+                    continue;
+                }
+                let (first, last) = row.columns();
+                let mut previous_wall = None;
+                for col in first..=last {
+                    let point = cell(row.depth, col);
+                    let in_bounds = self.point_in_bounds(point);
+                    let wall = !in_bounds || self.grid.get_point(point);
+                    let distance = (col as i64).pow(2) + (row.depth as i64).pow(2);
+                    let in_radius = distance <= (radius as i64).pow(2);
+                    if in_bounds && in_radius && (wall || row.is_symmetric(col)) {
+                        visible.set_point(point, true);
+                    }
+                    match previous_wall {
+                        Some(true) if !wall => row.start = slope(row.depth, col),
+                        Some(false) if wall => rows.push(Row {
+                            end: slope(row.depth, col),
+                            ..row.next()
+                        }),
+                        _ => {}
+                    }
+                    previous_wall = Some(wall);
+                }
+                if previous_wall == Some(false) {
+                    rows.push(row.next());
+                }
+            }
+        }
+        visible
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP: &str = "
+        ..........#.........
+        ...#......#.........
+        ..####....#....###..
+        ..#.......#......#..
+        ..#...#####......#..
+        ..#..............#..
+        ..######...#######..
+        .........#..........
+        .........#..........
+    ";
+
+    #[test]
+    fn walls_cast_shadows() {
+        let pathing_grid = PathingGrid::from_fn(7, 5, |x, y| (x, y) == (3, 2));
+        let origin = Point::new(1, 2);
+        let visible = pathing_grid.field_of_view(origin, 10);
+        assert!(visible.get(3, 2));
+        assert!(!visible.get(4, 2) && !visible.get(6, 2));
+        assert!(visible.get(6, 0) && visible.get(6, 4) && visible.get(0, 0));
+        let near = pathing_grid.field_of_view(origin, 2);
+        assert!(near.get(3, 2) && near.get(2, 3));
+        assert!(!near.get(3, 4) && !near.get(6, 0));
+        assert!(!pathing_grid
+            .field_of_view(Point::new(7, 0), 10)
+            .values
+            .iter()
+            .any(|&bits| bits != 0));
+        let far = pathing_grid.field_of_view(origin, i32::MAX);
+        assert_eq!(far.values, pathing_grid.field_of_view(origin, 10).values);
+    }
+
+    /// Free cells see each other both ways.
+    #[test]
+    fn is_symmetric() {
+        let pathing_grid: PathingGrid = MAP.parse().unwrap();
+        let (w, h) = (pathing_grid.width(), pathing_grid.height());
+        let free = (0..w)
+            .flat_map(|x| (0..h).map(move |y| Point::new(x as i32, y as i32)))
+            .filter(|p| !pathing_grid.grid().get_point(*p))
+            .collect::<Vec<_>>();
+        let views = free
+            .iter()
+            .map(|p| pathing_grid.field_of_view(*p, 8))
+            .collect::<Vec<_>>();
+        for (a, view_a) in free.iter().zip(&views) {
+            for (b, view_b) in free.iter().zip(&views) {
+                assert_eq!(view_a.get_point(*b), view_b.get_point(*a), "{} {}", a, b);
+            }
+        }
+    }
+}
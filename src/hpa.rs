@@ -0,0 +1,655 @@
+//! Hierarchical pathfinding ([HPA*](https://webdocs.cs.ualberta.ca/~mmueller/ps/hpastar.pdf)) for
+//! queries across huge maps. The grid is divided into square clusters, and entrances are placed
+//! along the borders between neighbouring clusters where both sides are free. The entrances form
+//! an abstract graph, connected across borders and within each cluster by the costs of paths found
+//! with JPS inside the cluster. Queries search this much smaller graph and then refine each of its
+//! edges into waypoints.
+//!
+//! Diagonal moves which pass between two blocked cells, as allowed by
+//! [DiagonalPolicy::Always](crate::DiagonalPolicy::Always), cross a border or the corner between
+//! diagonally neighbouring clusters without a free pair of cells on either side, so each of them
+//! gets an entrance of its own. Every move between clusters then passes an entrance, and a query
+//! for which the abstract graph has no route has no path at all.
+//!
+//! Paths found this way are close to, but not always, the shortest.
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use grid_util::direction::Direction;
+use grid_util::grid::{BoolGrid, Grid};
+use grid_util::point::Point;
+use itertools::Itertools;
+use log::info;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::astar_jps::astar_jps_with_config;
+use crate::{PathError, PathingGrid, SearchConfig, SearchStats};
+
+/// Width and height of clusters if none is given.
+pub const DEFAULT_CLUSTER_SIZE: usize = 32;
+/// Runs of free cells along a border of at least this length get an entrance at both ends rather
+/// than a single one in the middle.
+const LONG_RUN: usize = 6;
+/// The offsets of the neighbouring clusters whose borders with a cluster it holds the entrances
+/// of: the orthogonal neighbours in the positive x and y directions, and the diagonal neighbours
+/// in the positive x direction which only share a corner with it.
+const AXES: [(i32, i32); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+/// A cluster with a [PathingGrid] of its own, in which points are relative to its origin.
+#[derive(Clone, Debug)]
+struct Cluster {
+    origin: Point,
+    grid: PathingGrid,
+    /// The edges from each entrance in the cluster, to the other entrances in the cluster and
+    /// across the border.
+    edges: FxHashMap<Point, Vec<(Point, i32)>>,
+}
+
+impl Cluster {
+    /// Finds a path within the cluster between two points of the grid.
+    fn local_path(&self, a: &Point, b: &Point) -> Option<(Vec<Point>, i32)> {
+        let result = self
+            .grid
+            .get_path_result_single_goal(
+                *a - self.origin,
+                *b - self.origin,
+                &SearchConfig::optimal(),
+            )
+            .ok()?;
+        let waypoints = result
+            .waypoints
+            .into_iter()
+            .map(|p| p + self.origin)
+            .collect();
+        Some((waypoints, result.cost))
+    }
+    /// Finds the costs of the shortest paths within the cluster from a point of the grid to each of
+    /// the reachable targets, with a single Dijkstra search over jump points which stop at every
+    /// target.
+    fn local_costs(&self, from: &Point, targets: &[Point]) -> Vec<(Point, i32)> {
+        let origin = self.origin;
+        let start = *from - origin;
+        // Only targets in the same component are searched for, so that the search ends as soon as
+        // all of them are settled.
+        let local = targets
+            .iter()
+            .map(|p| *p - origin)
+            .filter(|p| !self.grid.unreachable(&start, p))
+            .collect::<FxHashSet<_>>();
+        let is_target = |p: &Point| local.contains(p);
+        let mut remaining = local.len();
+        let mut best = FxHashMap::default();
+        best.insert(start, (0, None));
+        let mut to_see = BinaryHeap::from([Reverse((0, start.x, start.y))]);
+        while let Some(Reverse((cost, x, y))) = to_see.pop() {
+            if remaining == 0 {
+                break;
+            }
+            let node = Point::new(x, y);
+            let (c, parent) = best[&node];
+            if cost > c {
+                continue;
+            }
+            if is_target(&node) {
+                remaining -= 1;
+            }
+            let successors =
+                self.grid
+                    .jps_successors(parent.as_ref(), &node, &is_target, None, false, &mut 0);
+            for (successor, move_cost) in successors {
+                let new_cost = cost + move_cost;
+                if best.get(&successor).map_or(true, |&(c, _)| new_cost < c) {
+                    best.insert(successor, (new_cost, Some(node)));
+                    to_see.push(Reverse((new_cost, successor.x, successor.y)));
+                }
+            }
+        }
+        targets
+            .iter()
+            .filter(|p| local.contains(&(**p - origin)))
+            .map(|p| (*p, best[&(*p - origin)].0))
+            .collect()
+    }
+}
+
+/// [HierarchicalGrid] keeps an abstract graph of cluster entrances on top of a [PathingGrid]. As
+/// cells are set, only the clusters around them are rebuilt.
+/// Implements [Grid] by building on [PathingGrid].
+#[derive(Clone, Debug)]
+pub struct HierarchicalGrid {
+    pathing_grid: PathingGrid,
+    cluster_size: usize,
+    columns: usize,
+    clusters: Vec<Cluster>,
+    /// The entrances between each cluster and its neighbours along each of the [AXES], as pairs
+    /// of a point in the cluster and the adjacent point in the neighbour.
+    entrances: Vec<[Vec<(Point, Point)>; 4]>,
+}
+
+impl HierarchicalGrid {
+    /// Builds the abstract graph on top of the grid, regenerating its components if they are
+    /// dirty.
+    pub fn new(mut pathing_grid: PathingGrid, cluster_size: usize) -> HierarchicalGrid {
+        assert!(cluster_size > 0, "clusters must not be empty");
+        pathing_grid.update();
+        let columns = pathing_grid.width().div_ceil(cluster_size);
+        let rows = pathing_grid.height().div_ceil(cluster_size);
+        let mut hierarchical_grid = HierarchicalGrid {
+            pathing_grid,
+            cluster_size,
+            columns,
+            clusters: Vec::with_capacity(columns * rows),
+            entrances: vec![Default::default(); columns * rows],
+        };
+        for (cy, cx) in (0..rows).cartesian_product(0..columns) {
+            let cluster = hierarchical_grid.build_cluster(cx, cy);
+            hierarchical_grid.clusters.push(cluster);
+        }
+        for ix in 0..columns * rows {
+            hierarchical_grid.entrances[ix] =
+                [0, 1, 2, 3].map(|axis| hierarchical_grid.find_entrances(ix, axis));
+        }
+        for ix in 0..columns * rows {
+            hierarchical_grid.connect_cluster(ix);
+        }
+        hierarchical_grid
+    }
+    /// The underlying grid.
+    pub fn pathing_grid(&self) -> &PathingGrid {
+        &self.pathing_grid
+    }
+    /// The width and height of the clusters, apart from those at the far edges of the grid.
+    pub fn cluster_size(&self) -> usize {
+        self.cluster_size
+    }
+    /// The number of nodes in the abstract graph.
+    pub fn node_count(&self) -> usize {
+        self.clusters
+            .iter()
+            .map(|cluster| cluster.edges.len())
+            .sum()
+    }
+    fn cluster_ix(&self, point: &Point) -> usize {
+        point.x as usize / self.cluster_size + point.y as usize / self.cluster_size * self.columns
+    }
+    /// The index of the cluster at the given offset in clusters from another, if there is one.
+    fn offset_cluster(&self, ix: usize, (dx, dy): (i32, i32)) -> Option<usize> {
+        let rows = self.clusters.len() / self.columns;
+        let cx = (ix % self.columns) as i32 + dx;
+        let cy = (ix / self.columns) as i32 + dy;
+        (cx >= 0 && cy >= 0 && (cx as usize) < self.columns && (cy as usize) < rows)
+            .then(|| cx as usize + cy as usize * self.columns)
+    }
+    /// The index of the neighbouring cluster along an axis, see [AXES].
+    fn next_cluster(&self, ix: usize, axis: usize) -> Option<usize> {
+        self.offset_cluster(ix, AXES[axis])
+    }
+    /// The index of the neighbouring cluster against an axis, see [AXES].
+    fn previous_cluster(&self, ix: usize, axis: usize) -> Option<usize> {
+        let (dx, dy) = AXES[axis];
+        self.offset_cluster(ix, (-dx, -dy))
+    }
+    fn build_cluster(&self, cx: usize, cy: usize) -> Cluster {
+        let (x0, y0) = (cx * self.cluster_size, cy * self.cluster_size);
+        let width = self.cluster_size.min(self.pathing_grid.width() - x0);
+        let height = self.cluster_size.min(self.pathing_grid.height() - y0);
+        let mut grid = BoolGrid::new(width, height, false);
+        for (x, y) in (0..width).cartesian_product(0..height) {
+            if self.pathing_grid.get(x0 + x, y0 + y) {
+                grid.set(x, y, true);
+            }
+        }
+        Cluster {
+            origin: Point::new(x0 as i32, y0 as i32),
+            grid: PathingGrid::from_parts(
+                grid,
+                self.pathing_grid.connectivity(),
+                self.pathing_grid.diagonal_policy(),
+                self.pathing_grid.cost_model(),
+            ),
+            edges: FxHashMap::default(),
+        }
+    }
+    /// Whether a diagonal move from a to b passes between two blocked cells. Such a move is not
+    /// covered by the entrances placed in runs of free cells.
+    fn squeezes_between(&self, a: &Point, b: &Point) -> bool {
+        let dir = a.dir_obj(b);
+        self.pathing_grid.can_step(a, dir)
+            && !self.pathing_grid.can_move_to(*a + dir.x_dir())
+            && !self.pathing_grid.can_move_to(*a + dir.y_dir())
+    }
+    /// Places entrances along the border between a cluster and its next neighbour on an axis, in
+    /// every run of cells which are free on both sides and at every diagonal move which squeezes
+    /// between blocked cells across the border. Diagonal neighbours only share a corner, which
+    /// gets an entrance if the move across it squeezes between blocked cells.
+    fn find_entrances(&self, ix: usize, axis: usize) -> Vec<(Point, Point)> {
+        let Some(next) = self.next_cluster(ix, axis) else {
+            return Vec::new();
+        };
+        let (cluster, next) = (&self.clusters[ix], &self.clusters[next]);
+        let (dx, dy) = AXES[axis];
+        if dx != 0 && dy != 0 {
+            let (w, h) = (cluster.grid.width() as i32, cluster.grid.height() as i32);
+            let a = cluster.origin + Point::new(w - 1, if dy > 0 { h - 1 } else { 0 });
+            let b = a + Point::new(dx, dy);
+            return if self.squeezes_between(&a, &b) {
+                vec![(a, b)]
+            } else {
+                Vec::new()
+            };
+        }
+        let (step, along, len) = if axis == 0 {
+            (Direction::EAST, Direction::NORTH, cluster.grid.height())
+        } else {
+            (Direction::NORTH, Direction::EAST, cluster.grid.width())
+        };
+        let border = next.origin - Point::from(step);
+        let pair = |i: usize| {
+            let a = border + Point::from(along) * i as i32;
+            (a, a + step)
+        };
+        let free = |i: usize| {
+            let (a, b) = pair(i);
+            !self.pathing_grid.get_point(a) && !self.pathing_grid.get_point(b)
+        };
+        let mut entrances = Vec::new();
+        let mut i = 0;
+        while i < len {
+            if !free(i) {
+                i += 1;
+                continue;
+            }
+            let run_start = i;
+            while i < len && free(i) {
+                i += 1;
+            }
+            if i - run_start < LONG_RUN {
+                entrances.push(pair((run_start + i - 1) / 2));
+            } else {
+                entrances.extend([pair(run_start), pair(i - 1)]);
+            }
+        }
+        for (i, j) in (0..len).flat_map(|i| [(i, i.wrapping_sub(1)), (i, i + 1)]) {
+            let (a, b) = (pair(i).0, pair(j).1);
+            if j < len && self.squeezes_between(&a, &b) {
+                entrances.push((a, b));
+            }
+        }
+        entrances
+    }
+    /// Connects the entrances of a cluster to each other and across its borders.
+    fn connect_cluster(&mut self, ix: usize) {
+        let mut edges: FxHashMap<Point, Vec<(Point, i32)>> = FxHashMap::default();
+        let cost = |a: &Point, b: &Point| self.pathing_grid.step_cost(a.dir_obj(b));
+        for axis in 0..AXES.len() {
+            for &(a, b) in &self.entrances[ix][axis] {
+                edges.entry(a).or_default().push((b, cost(&a, &b)));
+            }
+            if let Some(previous) = self.previous_cluster(ix, axis) {
+                for &(a, b) in &self.entrances[previous][axis] {
+                    edges.entry(b).or_default().push((a, cost(&b, &a)));
+                }
+            }
+        }
+        let cluster = &self.clusters[ix];
+        let nodes = edges
+            .keys()
+            .copied()
+            .sorted_by_key(|p| (p.y, p.x))
+            .collect_vec();
+        for (i, a) in nodes.iter().enumerate() {
+            for (b, cost) in cluster.local_costs(a, &nodes[i + 1..]) {
+                edges.get_mut(a).unwrap().push((b, cost));
+                edges.get_mut(&b).unwrap().push((*a, cost));
+            }
+        }
+        self.clusters[ix].edges = edges;
+    }
+    /// Updates a batch of positions on the grid like [PathingGrid::set_many], then rebuilds the
+    /// clusters containing them and the clusters on both sides of every entrance which changed.
+    pub fn set_many<I>(&mut self, cells: I)
+    where
+        I: IntoIterator<Item = (Point, bool)>,
+    {
+        let mut changes = FxHashMap::default();
+        for (point, blocked) in cells {
+            if self.pathing_grid.point_in_bounds(point) {
+                changes.insert(point, blocked);
+            }
+        }
+        changes.retain(|point, blocked| self.pathing_grid.get_point(*point) != *blocked);
+        if changes.is_empty() {
+            return;
+        }
+        self.pathing_grid
+            .set_many(changes.iter().map(|(&p, &b)| (p, b)));
+        let mut by_cluster: FxHashMap<usize, Vec<(Point, bool)>> = FxHashMap::default();
+        for (point, blocked) in changes {
+            by_cluster
+                .entry(self.cluster_ix(&point))
+                .or_default()
+                .push((point, blocked));
+        }
+        let mut rebuilt = by_cluster.keys().copied().collect_vec();
+        for (&ix, cells) in &by_cluster {
+            let origin = self.clusters[ix].origin;
+            self.clusters[ix]
+                .grid
+                .set_many(cells.iter().map(|&(p, b)| (p - origin, b)));
+        }
+        // Entrances at a corner also depend on the two other clusters sharing it, so the borders
+        // of every cluster around a changed one are checked.
+        let this = &*self;
+        let owners = by_cluster
+            .keys()
+            .flat_map(|&ix| {
+                (-1..=1)
+                    .cartesian_product(-1..=1)
+                    .filter_map(move |offset| this.offset_cluster(ix, offset))
+            })
+            .unique()
+            .collect_vec();
+        for owner in owners {
+            for axis in 0..AXES.len() {
+                let Some(next) = self.next_cluster(owner, axis) else {
+                    continue;
+                };
+                let entrances = self.find_entrances(owner, axis);
+                if entrances != self.entrances[owner][axis] {
+                    self.entrances[owner][axis] = entrances;
+                    rebuilt.extend([owner, next]);
+                }
+            }
+        }
+        for ix in rebuilt.into_iter().unique() {
+            self.connect_cluster(ix);
+        }
+    }
+    /// Computes waypoints from start to goal by searching the abstract graph and refining its
+    /// edges with JPS inside the clusters. Queries with a positive
+    /// [goal tolerance](SearchConfig::goal_tolerance) are answered by
+    /// [PathingGrid::get_waypoints_single_goal] instead.
+    pub fn get_waypoints_single_goal(
+        &self,
+        start: Point,
+        goal: Point,
+        config: &SearchConfig,
+    ) -> Result<Vec<Point>, PathError> {
+        let pathing_grid = &self.pathing_grid;
+        pathing_grid.check_start(&start)?;
+        if config.goal_tolerance > 0 {
+            return pathing_grid.get_waypoints_single_goal(start, goal, config);
+        }
+        if pathing_grid.unreachable(&start, &goal) {
+            info!("{} is not reachable from {}", goal, start);
+            return Err(pathing_grid.unreachable_error());
+        }
+        let (start_cluster, goal_cluster) = (
+            &self.clusters[self.cluster_ix(&start)],
+            &self.clusters[self.cluster_ix(&goal)],
+        );
+        // The start and goal are connected to the entrances of their clusters for this query.
+        let mut targets = start_cluster.edges.keys().copied().collect_vec();
+        if start_cluster.origin == goal_cluster.origin {
+            targets.push(goal);
+        }
+        let start_edges = start_cluster.local_costs(&start, &targets);
+        let goal_nodes = goal_cluster.edges.keys().copied().collect_vec();
+        let goal_edges = goal_cluster
+            .local_costs(&goal, &goal_nodes)
+            .into_iter()
+            .collect::<FxHashMap<_, _>>();
+        let mut stats = SearchStats::default();
+        let result = astar_jps_with_config(
+            &start,
+            |_, node| {
+                let mut successors = Vec::new();
+                if *node == start {
+                    successors.extend(start_edges.iter().copied());
+                }
+                if let Some(edges) = self.clusters[self.cluster_ix(node)].edges.get(node) {
+                    successors.extend(edges.iter().copied());
+                }
+                successors.extend(goal_edges.get(node).map(|&cost| (goal, cost)));
+                successors
+            },
+            |node| {
+                let distance = pathing_grid.cost_distance(node, &goal);
+                (distance as f64 * config.heuristic_weight as f64) as i32
+            },
+            |node| *node == goal,
+            config,
+            &mut stats,
+        );
+        // Every move between clusters passes an entrance, so without a route there is no path.
+        let (nodes, _) = result.ok_or_else(|| pathing_grid.search_failure(config, &stats))?;
+        let mut nodes = nodes.collect_vec();
+        nodes.reverse();
+        let mut waypoints = vec![start];
+        for (a, b) in nodes.into_iter().tuple_windows() {
+            let (ia, ib) = (self.cluster_ix(&a), self.cluster_ix(&b));
+            if ia == ib {
+                // The edge cannot be refined if the grid of the cluster no longer matches it.
+                let (path, _) = self.clusters[ia].local_path(&a, &b).ok_or_else(|| {
+                    info!("No local path from {} to {} within its cluster", a, b);
+                    pathing_grid.unreachable_error()
+                })?;
+                waypoints.extend(path.into_iter().skip(1));
+            } else {
+                waypoints.push(b);
+            }
+        }
+        Ok(waypoints)
+    }
+    /// Like [get_waypoints_single_goal](Self::get_waypoints_single_goal), but returns a path which
+    /// can be followed step by step.
+    pub fn get_path_single_goal(
+        &self,
+        start: Point,
+        goal: Point,
+        config: &SearchConfig,
+    ) -> Result<Vec<Point>, PathError> {
+        self.get_waypoints_single_goal(start, goal, config)
+            .map(|waypoints| self.pathing_grid.waypoints_to_path(waypoints))
+    }
+}
+
+impl Grid<bool> for HierarchicalGrid {
+    /// Builds a grid with clusters of [DEFAULT_CLUSTER_SIZE].
+    fn new(width: usize, height: usize, default_value: bool) -> Self {
+        HierarchicalGrid::new(
+            PathingGrid::new(width, height, default_value),
+            DEFAULT_CLUSTER_SIZE,
+        )
+    }
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.pathing_grid.get(x, y)
+    }
+    /// Updates a position on the grid, see [set_many](HierarchicalGrid::set_many).
+    fn set(&mut self, x: usize, y: usize, blocked: bool) {
+        self.set_many([(Point::new(x as i32, y as i32), blocked)]);
+    }
+    fn width(&self) -> usize {
+        self.pathing_grid.width()
+    }
+    fn height(&self) -> usize {
+        self.pathing_grid.height()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Connectivity, CostModel, DiagonalPolicy};
+
+    const MAP: &str = "
+        ........#.......#...............
+        ........#.......#..........#....
+        ..####..#..######..........#....
+        ........#..................#....
+        ........#######.....########....
+        ...............#................
+        #####..........#......#.........
+        ....#..........#......#.........
+        ....#....#######......#######...
+        ....#.....................#.....
+        ....######.....#..........#.....
+        ...............#..........#.....
+    ";
+
+    fn follow(pathing_grid: &PathingGrid, waypoints: Vec<Point>) -> i32 {
+        let path = pathing_grid.waypoints_to_path(waypoints);
+        path.iter()
+            .tuple_windows()
+            .map(|(a, b)| {
+                assert!(pathing_grid.can_step(a, a.dir_obj(b)), "{} -> {}", a, b);
+                pathing_grid.step_cost(a.dir_obj(b))
+            })
+            .sum()
+    }
+
+    /// Refined paths can be followed on the grid and are close to the shortest paths.
+    #[test]
+    fn finds_near_optimal_paths() {
+        let mut pathing_grid: PathingGrid = MAP.parse().unwrap();
+        pathing_grid.set_diagonal_policy(DiagonalPolicy::BothSidesFree);
+        pathing_grid.set_cost_model(CostModel::Octile);
+        let hierarchical_grid = HierarchicalGrid::new(pathing_grid.clone(), 8);
+        assert!(hierarchical_grid.node_count() > 0);
+        let config = SearchConfig::optimal();
+        for (start, goal) in [
+            (Point::new(0, 0), Point::new(31, 11)),
+            (Point::new(0, 11), Point::new(31, 0)),
+            (Point::new(5, 7), Point::new(24, 9)),
+            (Point::new(1, 1), Point::new(3, 3)),
+        ] {
+            let waypoints = hierarchical_grid
+                .get_waypoints_single_goal(start, goal, &config)
+                .unwrap();
+            assert_eq!(waypoints.first(), Some(&start));
+            assert_eq!(waypoints.last(), Some(&goal));
+            let cost = follow(&pathing_grid, waypoints);
+            let optimal = pathing_grid
+                .get_path_result_single_goal(start, goal, &config)
+                .unwrap()
+                .cost;
+            assert!(
+                cost >= optimal && cost * 4 <= optimal * 5,
+                "{} vs {}",
+                cost,
+                optimal
+            );
+        }
+    }
+
+    /// Setting cells gives the same abstract graph as building it from scratch.
+    #[test]
+    fn rebuilds_affected_clusters() {
+        let mut hierarchical_grid = HierarchicalGrid::new(MAP.parse().unwrap(), 8);
+        for (x, y, blocked) in [
+            (8, 3, false),
+            (7, 4, true),
+            (16, 5, true),
+            (23, 8, false),
+            (0, 0, true),
+            // Leaves only a diagonal move across the corner between four clusters.
+            (8, 7, true),
+            (7, 8, true),
+            (8, 7, false),
+        ] {
+            hierarchical_grid.set(x, y, blocked);
+            let fresh = HierarchicalGrid::new(hierarchical_grid.pathing_grid().clone(), 8);
+            assert_eq!(hierarchical_grid.entrances, fresh.entrances);
+            for (cluster, fresh) in hierarchical_grid.clusters.iter().zip(&fresh.clusters) {
+                assert_eq!(cluster.edges, fresh.edges);
+                assert_eq!(cluster.grid.grid().values, fresh.grid.grid().values);
+            }
+        }
+        assert!(hierarchical_grid
+            .get_waypoints_single_goal(
+                Point::new(1, 1),
+                Point::new(31, 11),
+                &SearchConfig::optimal()
+            )
+            .is_ok());
+    }
+
+    /// Clusters which are only connected by diagonal moves between blocked cells, across a border
+    /// or a corner, are connected through entrances and never need a search of the whole grid.
+    #[test]
+    fn crosses_between_blocked_cells() {
+        for (map, cluster_size) in [
+            (
+                "
+                ...#....
+                ...#....
+                ....#...
+                ....#...
+                ",
+                4,
+            ),
+            (
+                "
+                ..##
+                ..##
+                ##..
+                ##..
+                ",
+                2,
+            ),
+        ] {
+            let pathing_grid: PathingGrid = map.parse().unwrap();
+            let hierarchical_grid = HierarchicalGrid::new(pathing_grid.clone(), cluster_size);
+            assert!(hierarchical_grid.node_count() > 0);
+            let config = SearchConfig::optimal();
+            let start = Point::new(0, 0);
+            let goal = Point::new(pathing_grid.width() as i32 - 1, 3);
+            let waypoints = hierarchical_grid
+                .get_waypoints_single_goal(start, goal, &config)
+                .unwrap();
+            let optimal = pathing_grid
+                .get_path_result_single_goal(start, goal, &config)
+                .unwrap()
+                .cost;
+            assert_eq!(follow(&pathing_grid, waypoints), optimal);
+        }
+    }
+
+    /// Queries fail exactly when there is no path and otherwise give paths which can be followed,
+    /// for every movement rule.
+    #[test]
+    fn finds_every_reachable_goal() {
+        let blocked = |x: usize, y: usize| ((x * 31 + y * 17) ^ (x * y)) % 5 < 2;
+        for (connectivity, policy) in [
+            (Connectivity::Four, DiagonalPolicy::Always),
+            (Connectivity::Eight, DiagonalPolicy::Always),
+            (Connectivity::Eight, DiagonalPolicy::OneSideFree),
+            (Connectivity::Eight, DiagonalPolicy::BothSidesFree),
+        ] {
+            let mut pathing_grid = PathingGrid::from_fn(30, 20, blocked);
+            pathing_grid.set_connectivity(connectivity);
+            pathing_grid.set_diagonal_policy(policy);
+            pathing_grid.update();
+            let hierarchical_grid = HierarchicalGrid::new(pathing_grid.clone(), 4);
+            let config = SearchConfig::default();
+            let start = Point::new(1, 1);
+            for (x, y) in (0..30).cartesian_product(0..20) {
+                let goal = Point::new(x, y);
+                let waypoints = hierarchical_grid.get_waypoints_single_goal(start, goal, &config);
+                assert_eq!(
+                    waypoints.is_ok(),
+                    pathing_grid
+                        .get_waypoints_single_goal(start, goal, &config)
+                        .is_ok(),
+                    "{} {:?} {:?}",
+                    goal,
+                    connectivity,
+                    policy
+                );
+                if let Ok(waypoints) = waypoints {
+                    follow(&pathing_grid, waypoints);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,391 @@
+//! JPS+ preprocessing, which stores for every cell and direction how far a jump in that direction
+//! goes. Jumps then become table lookups instead of scanning the grid cell by cell, which pays off
+//! on maps that change rarely compared to how often they are searched.
+//!
+//! Once [enabled](PathingGrid::enable_jump_table), the table is repaired whenever cells are
+//! [set](PathingGrid::set_many), only revisiting the entries which depend on the changed cells.
+//! Changing the [Connectivity] or [DiagonalPolicy](crate::DiagonalPolicy) rebuilds it. Searches use
+//! the table for queries with a single goal and no goal tolerance, and scan the grid otherwise. The
+//! table is not part of snapshots or serialized grids.
+//!
+//! Distances are stored as `i16`, so the table takes 16 bytes per cell on top of the grid. Longer
+//! jumps are stored as [MAX_DISTANCE] and looked up in pieces, continuing from the entry of the
+//! cell `MAX_DISTANCE - 1` steps further along.
+use std::collections::VecDeque;
+
+use grid_util::direction::Direction;
+use grid_util::grid::Grid;
+use grid_util::point::Point;
+use rustc_hash::FxHashSet;
+
+use crate::{Connectivity, PathingGrid};
+
+/// Directions in an order in which each only depends on the directions before it: horizontal
+/// jumps scan nothing else, vertical jumps on a 4-connected grid scan horizontally and diagonal
+/// jumps scan along both of their components.
+const DIRECTIONS: [Direction; 8] = [
+    Direction::EAST,
+    Direction::WEST,
+    Direction::NORTH,
+    Direction::SOUTH,
+    Direction::NORTHEAST,
+    Direction::SOUTHEAST,
+    Direction::SOUTHWEST,
+    Direction::NORTHWEST,
+];
+
+/// The largest distance stored in a [JumpTable], standing for a jump of at least this many steps.
+pub const MAX_DISTANCE: i32 = i16::MAX as i32;
+
+/// Jump distances for every cell and direction. A positive distance `k` means a jump from the cell
+/// ends at the jump point `k` steps away, while a distance of `-k` means the jump runs into a wall
+/// after `k` steps without finding one. Distances of [MAX_DISTANCE] in either sign only tell that
+/// the jump is at least that long.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JumpTable {
+    distances: Vec<[i16; 8]>,
+}
+
+impl JumpTable {
+    /// The jump distance from the cell at index ix in the given direction.
+    pub fn distance(&self, ix: usize, dir: Direction) -> i32 {
+        self.distances[ix][dir.num() as usize] as i32
+    }
+    /// Computes the table for the whole grid, sweeping against each direction so that the entry of
+    /// the next cell along it is always known.
+    pub(crate) fn generate(pathing_grid: &PathingGrid) -> JumpTable {
+        let (w, h) = (pathing_grid.width(), pathing_grid.height());
+        let mut table = JumpTable {
+            distances: vec![[0; 8]; w * h],
+        };
+        let order = |len: usize, delta: i32| {
+            let values = (0..len as i32).collect::<Vec<_>>();
+            if delta > 0 {
+                values.into_iter().rev().collect::<Vec<_>>()
+            } else {
+                values
+            }
+        };
+        for dir in table_directions(pathing_grid) {
+            let xs = order(w, dir.x());
+            for y in order(h, dir.y()) {
+                for &x in &xs {
+                    let point = Point::new(x, y);
+                    let distance = table.compute(pathing_grid, &point, dir);
+                    table.distances[pathing_grid.get_ix_point(&point)][dir.num() as usize] =
+                        distance as i16;
+                }
+            }
+        }
+        table
+    }
+    /// Recomputes the entries affected by changes to the given cells. Entries close to a changed
+    /// cell are recomputed directly, and every entry that changes in turn queues the entries which
+    /// read it.
+    pub(crate) fn repair(&mut self, pathing_grid: &PathingGrid, changed: &[Point]) {
+        let mut queue = VecDeque::new();
+        let mut queued = FxHashSet::default();
+        // A jump reads the cells around its first step and the cell after it.
+        for point in changed {
+            for dx in -2..=2 {
+                for dy in -2..=2 {
+                    let p = Point::new(point.x + dx, point.y + dy);
+                    if pathing_grid.point_in_bounds(p) {
+                        for dir in table_directions(pathing_grid) {
+                            if queued.insert((p, dir)) {
+                                queue.push_back((p, dir));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        while let Some((point, dir)) = queue.pop_front() {
+            queued.remove(&(point, dir));
+            let ix = pathing_grid.get_ix_point(&point);
+            let distance = self.compute(pathing_grid, &point, dir);
+            if self.distance(ix, dir) == distance {
+                continue;
+            }
+            self.distances[ix][dir.num() as usize] = distance as i16;
+            for dependent in table_directions(pathing_grid) {
+                if dependent == dir
+                    || scanned_directions(pathing_grid, dependent).any(|scan| scan == dir)
+                {
+                    let p = point - Point::from(dependent);
+                    if pathing_grid.point_in_bounds(p) && queued.insert((p, dependent)) {
+                        queue.push_back((p, dependent));
+                    }
+                }
+            }
+        }
+    }
+    /// Computes a single entry from the entries of the next cell along the direction, mirroring
+    /// [PathingGrid::jump] without a goal. Entries saturate at [MAX_DISTANCE].
+    fn compute(&self, pathing_grid: &PathingGrid, point: &Point, dir: Direction) -> i32 {
+        if !pathing_grid.can_step(point, dir) {
+            return 0;
+        }
+        let next = *point + dir;
+        let next_ix = pathing_grid.get_ix_point(&next);
+        if pathing_grid.is_forced(dir, &next)
+            || scanned_directions(pathing_grid, dir).any(|scan| self.distance(next_ix, scan) > 0)
+        {
+            return 1;
+        }
+        let distance = match self.distance(next_ix, dir) {
+            distance if distance > 0 => distance + 1,
+            distance => distance - 1,
+        };
+        distance.clamp(-MAX_DISTANCE, MAX_DISTANCE)
+    }
+}
+
+/// The directions with entries in the table. Jumps on a 4-connected grid never move diagonally,
+/// so their diagonal entries are left at zero.
+fn table_directions(pathing_grid: &PathingGrid) -> impl Iterator<Item = Direction> {
+    let diagonal = pathing_grid.connectivity == Connectivity::Eight;
+    DIRECTIONS
+        .into_iter()
+        .filter(move |dir| diagonal || !dir.diagonal())
+}
+
+/// The directions scanned at every step of a jump in the given direction.
+fn scanned_directions(
+    pathing_grid: &PathingGrid,
+    dir: Direction,
+) -> impl Iterator<Item = Direction> {
+    let horizontal = (pathing_grid.connectivity == Connectivity::Four
+        && !PathingGrid::horizontal(dir))
+    .then_some([Direction::EAST, Direction::WEST]);
+    let components = dir.diagonal().then(|| [dir.x_dir(), dir.y_dir()]);
+    horizontal.into_iter().chain(components).flatten()
+}
+
+/// The number of steps in the given direction leading from one point to another, if any.
+pub(crate) fn steps_between(from: &Point, dir: Direction, to: &Point) -> Option<i32> {
+    let (dx, dy) = (to.x - from.x, to.y - from.y);
+    let steps = if dir.x() != 0 {
+        dx * dir.x()
+    } else {
+        dy * dir.y()
+    };
+    (steps > 0 && dx == steps * dir.x() && dy == steps * dir.y()).then_some(steps)
+}
+
+impl PathingGrid {
+    /// Computes the [JumpTable] used to speed up searches, see [jps_plus](crate::jps_plus).
+    pub fn enable_jump_table(&mut self) {
+        self.jump_table = Some(JumpTable::generate(self));
+    }
+    /// Drops the [JumpTable], after which searches scan the grid again.
+    pub fn disable_jump_table(&mut self) {
+        self.jump_table = None;
+    }
+    /// The [JumpTable] if it is enabled.
+    pub fn jump_table(&self) -> Option<&JumpTable> {
+        self.jump_table.as_ref()
+    }
+    /// Rebuilds the jump table if it is enabled, for when the rules for moving change.
+    pub(crate) fn regenerate_jump_table(&mut self) {
+        if self.jump_table.is_some() {
+            self.enable_jump_table();
+        }
+    }
+    /// Repairs the jump table if it is enabled after the given cells have changed.
+    pub(crate) fn repair_jump_table(&mut self, changed: &[Point]) {
+        if let Some(mut table) = self.jump_table.take() {
+            table.repair(self, changed);
+            self.jump_table = Some(table);
+        }
+    }
+    /// The distance of the jump from initial in the given direction, following saturated entries
+    /// to the end of the jump. A jump passes the cells before its end without stopping, so the
+    /// jump from any of them ends at the same cell.
+    fn full_distance(&self, table: &JumpTable, initial: &Point, direction: Direction) -> i32 {
+        let mut skipped = 0;
+        loop {
+            let cell = *initial + Point::new(direction.x() * skipped, direction.y() * skipped);
+            match table.distance(self.get_ix_point(&cell), direction) {
+                distance if distance.abs() == MAX_DISTANCE => skipped += MAX_DISTANCE - 1,
+                distance if distance > 0 => return skipped + distance,
+                distance if distance < 0 => return distance - skipped,
+                _ => return 0,
+            }
+        }
+    }
+    /// Looks up the jump from initial in the given direction, giving the same result as
+    /// [jump](Self::jump) towards a single goal. Besides the jump point in the table, the jump
+    /// ends where the goal lies on the jump itself or on one of the lines scanned along the way.
+    pub(crate) fn table_jump(
+        &self,
+        table: &JumpTable,
+        initial: &Point,
+        cost: i32,
+        direction: Direction,
+        goal: &Point,
+    ) -> Option<(Point, i32)> {
+        let distance = self.full_distance(table, initial, direction);
+        let reach = distance.abs();
+        let step = |steps: i32| *initial + Point::new(direction.x() * steps, direction.y() * steps);
+        let mut stop = steps_between(initial, direction, goal).filter(|&steps| steps <= reach);
+        for scan in scanned_directions(self, direction) {
+            // Only the step which lines up with the goal can see it along the scanned direction.
+            let steps = if scan.x() != 0 {
+                (direction.y() != 0).then(|| (goal.y - initial.y) * direction.y())
+            } else {
+                (direction.x() != 0).then(|| (goal.x - initial.x) * direction.x())
+            };
+            if let Some(steps) = steps.filter(|&steps| steps > 0 && steps <= reach) {
+                let cell = step(steps);
+                let scan_reach = self.full_distance(table, &cell, scan).abs();
+                if steps_between(&cell, scan, goal).is_some_and(|s| s <= scan_reach) {
+                    stop = Some(stop.map_or(steps, |stop| stop.min(steps)));
+                }
+            }
+        }
+        let steps = match stop {
+            Some(steps) => steps,
+            None if distance > 0 => distance,
+            None => return None,
+        };
+        Some((step(steps), cost + self.step_cost(direction) * (steps - 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DiagonalPolicy, SearchConfig};
+
+    const MAP: &str = "
+        ......#.....
+        .##...#..#..
+        ..#......#..
+        ......##....
+        .#.#......#.
+        ....#..#....
+        ..#.....#...
+    ";
+
+    fn settings() -> Vec<(Connectivity, DiagonalPolicy)> {
+        vec![
+            (Connectivity::Four, DiagonalPolicy::Always),
+            (Connectivity::Eight, DiagonalPolicy::Always),
+            (Connectivity::Eight, DiagonalPolicy::OneSideFree),
+            (Connectivity::Eight, DiagonalPolicy::BothSidesFree),
+        ]
+    }
+
+    /// Table lookups agree with scanning jumps for every cell, direction and goal.
+    #[test]
+    fn matches_scanning_jumps() {
+        for (connectivity, policy) in settings() {
+            let mut pathing_grid: PathingGrid = MAP.parse().unwrap();
+            pathing_grid.set_connectivity(connectivity);
+            pathing_grid.set_diagonal_policy(policy);
+            pathing_grid.enable_jump_table();
+            let table = pathing_grid.jump_table().unwrap();
+            let (w, h) = (pathing_grid.width() as i32, pathing_grid.height() as i32);
+            let points = (0..w)
+                .flat_map(|x| (0..h).map(move |y| Point::new(x, y)))
+                .filter(|p| pathing_grid.can_move_to(*p))
+                .collect::<Vec<_>>();
+            for initial in &points {
+                for dir in DIRECTIONS {
+                    if connectivity == Connectivity::Four && dir.diagonal() {
+                        assert_eq!(table.distance(pathing_grid.get_ix_point(initial), dir), 0);
+                    }
+                    for goal in &points {
+                        let scanned =
+                            pathing_grid.jump(initial, 1, dir, &|p: &Point| p == goal, None, &mut 0);
+                        let looked_up = pathing_grid.table_jump(table, initial, 1, dir, goal);
+                        assert_eq!(looked_up, scanned, "{} {:?} to {}", initial, dir, goal);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Repairing the table after edits gives the same table as generating it again, and searches
+    /// using it find the same paths.
+    #[test]
+    fn repairs_after_edits() {
+        for (connectivity, policy) in settings() {
+            let mut pathing_grid: PathingGrid = MAP.parse().unwrap();
+            pathing_grid.set_connectivity(connectivity);
+            pathing_grid.set_diagonal_policy(policy);
+            pathing_grid.update();
+            pathing_grid.enable_jump_table();
+            let edits = [
+                (3, 3, true),
+                (6, 0, false),
+                (9, 2, false),
+                (1, 5, true),
+                (4, 6, true),
+            ];
+            for (x, y, blocked) in edits {
+                pathing_grid.set(x, y, blocked);
+                let mut fresh = pathing_grid.clone();
+                fresh.enable_jump_table();
+                assert_eq!(pathing_grid.jump_table(), fresh.jump_table());
+            }
+            let (start, goal) = (Point::new(0, 0), Point::new(11, 6));
+            let mut scanning = pathing_grid.clone();
+            scanning.disable_jump_table();
+            let config = SearchConfig::optimal();
+            let with_table = pathing_grid
+                .get_path_result_single_goal(start, goal, &config)
+                .unwrap();
+            let without = scanning
+                .get_path_result_single_goal(start, goal, &config)
+                .unwrap();
+            assert_eq!(with_table.waypoints, without.waypoints);
+            assert_eq!(with_table.cost, without.cost);
+            assert!(with_table.stats.jump_calls <= without.stats.jump_calls);
+        }
+    }
+
+    /// Jumps longer than an entry can hold agree with scanning jumps and survive repairs.
+    #[test]
+    fn follows_saturated_entries() {
+        let width = MAX_DISTANCE as usize + 2000;
+        let far = width as i32 - 1;
+        for (connectivity, policy) in settings() {
+            let mut pathing_grid = PathingGrid::new(width, 3, false);
+            pathing_grid.set_connectivity(connectivity);
+            pathing_grid.set_diagonal_policy(policy);
+            pathing_grid.set(width - 1000, 0, true);
+            pathing_grid.enable_jump_table();
+            let initials = [Point::new(0, 0), Point::new(0, 1), Point::new(far, 2)];
+            let goals = [
+                Point::new(far, 2),
+                Point::new(0, 2),
+                Point::new(MAX_DISTANCE, 1),
+                Point::new(MAX_DISTANCE - 1, 2),
+                Point::new(far - 500, 1),
+            ];
+            let edits = [(width - 1000, 0, false), (MAX_DISTANCE as usize, 1, true)];
+            for edit in [None].into_iter().chain(edits.map(Some)) {
+                if let Some((x, y, blocked)) = edit {
+                    pathing_grid.set(x, y, blocked);
+                    let mut fresh = pathing_grid.clone();
+                    fresh.enable_jump_table();
+                    // Not assert_eq, which would print both tables.
+                    assert!(pathing_grid.jump_table() == fresh.jump_table());
+                }
+                let table = pathing_grid.jump_table().unwrap();
+                for initial in &initials {
+                    for dir in DIRECTIONS {
+                        for goal in &goals {
+                            let reached = |p: &Point| p == goal;
+                            let scanned = pathing_grid.jump(initial, 1, dir, &reached, None, &mut 0);
+                            let looked_up = pathing_grid.table_jump(table, initial, 1, dir, goal);
+                            assert_eq!(looked_up, scanned, "{} {:?} to {}", initial, dir, goal);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
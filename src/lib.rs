@@ -6,7 +6,8 @@
 //! for speedy
 //! pathfinding. Note that this assumes a uniform-cost grid. Pre-computes
 //! [connected components](https://en.wikipedia.org/wiki/Component_(graph_theory))
-//! to avoid flood-filling behaviour if no path exists.
+//! to avoid flood-filling behaviour if no path exists. Grids with per-cell traversal costs are
+//! supported by [WeightedGrid](weighted::WeightedGrid) using a cost-aware A* search.
 use core::fmt;
 use std::collections::VecDeque;
 
@@ -15,14 +16,33 @@ use grid_util::grid::{BoolGrid, Grid, SimpleGrid};
 use grid_util::point::Point;
 use itertools::Itertools;
 use log::info;
-use petgraph::unionfind::UnionFind;
+use rustc_hash::FxHashMap;
 
-use crate::astar_jps::astar_jps;
+use crate::astar_jps::astar_jps_with_config;
+use crate::components::Components;
+use crate::jps_plus::JumpTable;
+pub use crate::astar_jps::{SearchStats, TieBreaking};
 
+pub mod any_angle;
+pub mod ascii;
 pub mod astar_jps;
+pub mod bidirectional;
+pub mod bitscan;
+pub mod components;
+pub mod dstar_lite;
+pub mod fov;
+pub mod hpa;
+pub mod jps_plus;
+pub mod line_of_sight;
+pub mod movingai;
+pub mod snapshot;
+#[cfg(feature = "serde")]
+mod serde_support;
+pub mod weighted;
 
 /// Turns waypoints into a path on the grid which can be followed step by step. Due to symmetry this
-/// is typically one of many ways to follow the waypoints.
+/// is typically one of many ways to follow the waypoints. This assumes 8-connectivity, see
+/// [PathingGrid::waypoints_to_path] for a version which respects the [Connectivity] of a grid.
 pub fn waypoints_to_path(waypoints: Vec<Point>) -> Vec<Point> {
     let mut waypoint_queue = waypoints.into_iter().collect::<VecDeque<Point>>();
     let mut path: Vec<Point> = Vec::new();
@@ -38,40 +58,373 @@ pub fn waypoints_to_path(waypoints: Vec<Point>) -> Vec<Point> {
     path
 }
 
-/// [PathingGrid] maintains information about components using a [UnionFind] structure in addition to the raw
-/// [bool] grid values in the [BoolGrid] that determine whether a space is occupied ([true]) or
-/// empty ([false]). It also records neighbours in [u8] format for fast lookups during search.
+/// Determines which cells are adjacent to each other, both during search and when generating
+/// components.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Connectivity {
+    /// 8-connected movement using the
+    /// [Moore neighbourhood](https://en.wikipedia.org/wiki/Moore_neighborhood), which allows
+    /// diagonal moves.
+    #[default]
+    Eight,
+    /// 4-connected movement using the
+    /// [von Neumann neighbourhood](https://en.wikipedia.org/wiki/Von_Neumann_neighborhood), which
+    /// only allows orthogonal moves.
+    Four,
+}
+
+/// Determines when a diagonal move is allowed on an 8-connected grid, based on the two orthogonal
+/// cells it passes between.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DiagonalPolicy {
+    /// Diagonal moves are always allowed, even between two blocked cells.
+    #[default]
+    Always,
+    /// Diagonal moves are allowed if at least one of the two orthogonal cells is free.
+    OneSideFree,
+    /// Diagonal moves are only allowed if both orthogonal cells are free, so corners are never cut.
+    BothSidesFree,
+}
+
+/// Cost of a straight move under [CostModel::Octile], in fixed point.
+pub const OCTILE_STRAIGHT_COST: i32 = 1000;
+/// Cost of a diagonal move under [CostModel::Octile], which is [OCTILE_STRAIGHT_COST] times the
+/// square root of two rounded down.
+pub const OCTILE_DIAGONAL_COST: i32 = 1414;
+
+/// Determines the cost of moves on the grid, which is also reflected by the heuristic.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CostModel {
+    /// Every move costs 1, so the cost of a path is its number of moves.
+    #[default]
+    Uniform,
+    /// Straight moves cost [OCTILE_STRAIGHT_COST] and diagonal moves cost [OCTILE_DIAGONAL_COST],
+    /// approximating Euclidean distances in fixed point.
+    Octile,
+}
+
+/// The outcome of a successful search.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PathResult {
+    /// The waypoints from start to goal, which can be turned into a full path using
+    /// [PathingGrid::waypoints_to_path].
+    pub waypoints: Vec<Point>,
+    /// The total cost of the path under the [CostModel].
+    pub cost: i32,
+    /// The goal which was reached. Note that the path ends within the
+    /// [goal tolerance](SearchConfig::goal_tolerance) of this goal.
+    pub goal: Point,
+    /// Statistics collected during the search.
+    pub stats: SearchStats,
+    /// The nodes expanded during the search in order, if
+    /// [recorded](SearchConfig::record_expanded).
+    pub expanded: Vec<Point>,
+}
+
+/// The reason a query did not produce a path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PathError {
+    /// The start lies outside of the grid.
+    StartOutOfBounds(Point),
+    /// The start is a blocked cell.
+    StartBlocked(Point),
+    /// No goals were given.
+    NoGoals,
+    /// No goal can be reached from the start since they are in different components, or the goal
+    /// lies outside of the grid. Another goal may still be reachable.
+    GoalUnreachable,
+    /// No path was found, but the components are dirty after a change of [Connectivity] or
+    /// [DiagonalPolicy], so this may be wrong. The components need to be regenerated using
+    /// [PathingGrid::update] before retrying.
+    ComponentsDirty,
+    /// The search stopped after reaching [max_expansions](SearchConfig::max_expansions).
+    BudgetExhausted,
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathError::StartOutOfBounds(p) => write!(f, "start {} is out of bounds", p),
+            PathError::StartBlocked(p) => write!(f, "start {} is blocked", p),
+            PathError::NoGoals => write!(f, "no goals were given"),
+            PathError::GoalUnreachable => write!(f, "goal is not reachable from the start"),
+            PathError::ComponentsDirty => write!(f, "components are dirty and need regenerating"),
+            PathError::BudgetExhausted => write!(f, "search exceeded its expansion budget"),
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
+/// [PathingGrid] maintains information about components using a [Components] labelling, which is
+/// kept up to date incrementally as cells are set, in addition to the raw [bool] grid values in
+/// the [BoolGrid] that determine whether a space is occupied ([true]) or empty ([false]). It also
+/// records neighbours in [u8] format for fast lookups during search.
 /// Implements [Grid] by building on [BoolGrid].
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct PathingGrid {
-    pub grid: BoolGrid,
-    pub neighbours: SimpleGrid<u8>,
-    pub components: UnionFind<usize>,
-    pub components_dirty: bool,
+    grid: BoolGrid,
+    neighbours: SimpleGrid<u8>,
+    /// The transpose of the grid, so that columns can be scanned as bit-packed rows.
+    transposed: BoolGrid,
+    components: Components,
+    components_dirty: bool,
+    connectivity: Connectivity,
+    diagonal_policy: DiagonalPolicy,
+    cost_model: CostModel,
+    jump_table: Option<JumpTable>,
 }
 
-const IMPROVED_PRUNING: bool = true;
 const HEURISTIC_FACTOR: f32 = 1.2;
 
-impl Default for PathingGrid {
-    fn default() -> PathingGrid {
-        PathingGrid {
-            grid: BoolGrid::default(),
-            neighbours: SimpleGrid::default(),
-            components: UnionFind::new(0),
-            components_dirty: false,
+/// Per-query settings which trade search speed for optimality.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchConfig {
+    /// Factor by which the heuristic is inflated. A weight of 1 guarantees shortest paths, larger
+    /// weights expand fewer nodes at the expense of longer paths.
+    pub heuristic_weight: f32,
+    /// Whether the
+    /// [improved pruning rules](https://www.researchgate.net/publication/287338108_Improving_jump_point_search)
+    /// are used, which expand diagonal jump points without forced neighbours immediately.
+    pub improved_pruning: bool,
+    /// Searches succeed once they reach a point within this move distance of a goal, which is
+    /// useful if the goal itself is blocked.
+    pub goal_tolerance: i32,
+    /// The search gives up after expanding this many nodes, if set.
+    pub max_expansions: Option<usize>,
+    /// Which node is expanded first among nodes with the same estimated cost.
+    pub tie_breaking: TieBreaking,
+    /// Whether the expanded nodes are recorded in [PathResult::expanded], which is useful for
+    /// visualising searches.
+    pub record_expanded: bool,
+}
+
+impl Default for SearchConfig {
+    fn default() -> SearchConfig {
+        SearchConfig {
+            heuristic_weight: HEURISTIC_FACTOR,
+            improved_pruning: true,
+            goal_tolerance: 0,
+            max_expansions: None,
+            tie_breaking: TieBreaking::default(),
+            record_expanded: false,
+        }
+    }
+}
+
+impl SearchConfig {
+    /// Uses an admissible heuristic so returned paths are shortest paths under the [CostModel].
+    pub fn optimal() -> SearchConfig {
+        SearchConfig {
+            heuristic_weight: 1.0,
+            ..SearchConfig::default()
+        }
+    }
+    /// Paths to one of the neighbours of the goal, which is useful if the goal itself is blocked.
+    pub fn approximate() -> SearchConfig {
+        SearchConfig {
+            goal_tolerance: 1,
+            ..SearchConfig::default()
         }
     }
 }
 
 impl PathingGrid {
+    /// The blocked cells of the grid. It can only be modified through [set](Self::set) and
+    /// [set_many](Self::set_many), which keep the neighbours, components and jump tables in sync.
+    pub fn grid(&self) -> &BoolGrid {
+        &self.grid
+    }
+    /// For every cell, a bit mask of the [Point::moore_neighbor] directions it can move to.
+    pub fn neighbours(&self) -> &SimpleGrid<u8> {
+        &self.neighbours
+    }
+    /// The connected components of the free cells.
+    pub fn components(&self) -> &Components {
+        &self.components
+    }
+    /// Whether the components are out of date since the [Connectivity] or [DiagonalPolicy] was
+    /// changed. Component queries like [unreachable](Self::unreachable) can then give wrong
+    /// answers, and path queries report [PathError::ComponentsDirty] instead of
+    /// [PathError::GoalUnreachable]. Call [update](Self::update) to regenerate them.
+    pub fn components_dirty(&self) -> bool {
+        self.components_dirty
+    }
+    /// The [Connectivity] used for search and component generation.
+    pub fn connectivity(&self) -> Connectivity {
+        self.connectivity
+    }
+    /// Changes the [Connectivity] of the grid. As this changes which cells are connected, the
+    /// components are flagged as dirty and the [JumpTable] is rebuilt.
+    pub fn set_connectivity(&mut self, connectivity: Connectivity) {
+        if self.connectivity != connectivity {
+            self.connectivity = connectivity;
+            self.components_dirty = true;
+            self.regenerate_jump_table();
+        }
+    }
+    /// The [DiagonalPolicy] used for search and component generation.
+    pub fn diagonal_policy(&self) -> DiagonalPolicy {
+        self.diagonal_policy
+    }
+    /// Changes the [DiagonalPolicy] of the grid. As this changes which cells are connected, the
+    /// components are flagged as dirty and the [JumpTable] is rebuilt.
+    pub fn set_diagonal_policy(&mut self, diagonal_policy: DiagonalPolicy) {
+        if self.diagonal_policy != diagonal_policy {
+            self.diagonal_policy = diagonal_policy;
+            self.components_dirty = true;
+            self.regenerate_jump_table();
+        }
+    }
+    /// The [CostModel] used to compute the cost of moves.
+    pub fn cost_model(&self) -> CostModel {
+        self.cost_model
+    }
+    /// Changes the [CostModel] used to compute the cost of moves.
+    pub fn set_cost_model(&mut self, cost_model: CostModel) {
+        self.cost_model = cost_model;
+    }
+    /// The cost of a single move in the given direction under the [CostModel].
+    pub fn step_cost(&self, dir: Direction) -> i32 {
+        match self.cost_model {
+            CostModel::Uniform => 1,
+            CostModel::Octile if dir.diagonal() => OCTILE_DIAGONAL_COST,
+            CostModel::Octile => OCTILE_STRAIGHT_COST,
+        }
+    }
+    /// The cost of a shortest path between two points on an empty grid under the [CostModel],
+    /// which is an admissible heuristic.
+    pub fn cost_distance(&self, a: &Point, b: &Point) -> i32 {
+        match self.cost_model {
+            CostModel::Uniform => self.move_distance(a, b),
+            CostModel::Octile => match self.connectivity {
+                Connectivity::Eight => {
+                    let dx = (a.x - b.x).abs();
+                    let dy = (a.y - b.y).abs();
+                    OCTILE_STRAIGHT_COST * dx.max(dy)
+                        + (OCTILE_DIAGONAL_COST - OCTILE_STRAIGHT_COST) * dx.min(dy)
+                }
+                Connectivity::Four => OCTILE_STRAIGHT_COST * a.manhattan_distance(b),
+            },
+        }
+    }
+    /// The cost of a shortest path on an empty grid from a point to the closest point within the
+    /// given move distance of the goal.
+    fn region_cost_distance(&self, a: &Point, goal: &Point, radius: i32) -> i32 {
+        match self.connectivity {
+            Connectivity::Eight => {
+                let closest = Point::new(
+                    a.x.clamp(goal.x - radius, goal.x + radius),
+                    a.y.clamp(goal.y - radius, goal.y + radius),
+                );
+                self.cost_distance(a, &closest)
+            }
+            Connectivity::Four => {
+                (self.cost_distance(a, goal) - radius * self.step_cost(Direction::NORTH)).max(0)
+            }
+        }
+    }
+    /// Heuristic towards the points within the given move distance of the goal, which is
+    /// admissible if the weight is at most 1.
+    fn heuristic(&self, a: &Point, goal: &Point, radius: i32, weight: f32) -> i32 {
+        (self.region_cost_distance(a, goal, radius) as f64 * weight as f64) as i32
+    }
+    /// Number of moves between two points on an empty grid, which is the
+    /// [Chebyshev distance](https://en.wikipedia.org/wiki/Chebyshev_distance) for 8-connectivity and
+    /// the [Manhattan distance](https://en.wikipedia.org/wiki/Taxicab_geometry) for 4-connectivity.
+    pub fn move_distance(&self, a: &Point, b: &Point) -> i32 {
+        match self.connectivity {
+            Connectivity::Eight => a.move_distance(b),
+            Connectivity::Four => a.manhattan_distance(b),
+        }
+    }
+    /// Turns waypoints into a path on the grid which can be followed step by step, only taking
+    /// diagonal steps if the grid is 8-connected and the [DiagonalPolicy] allows them. Diagonal
+    /// steps which would cut a corner are replaced by a step along a free side.
+    pub fn waypoints_to_path(&self, waypoints: Vec<Point>) -> Vec<Point> {
+        match self.connectivity {
+            Connectivity::Eight if self.diagonal_policy == DiagonalPolicy::Always => {
+                waypoints_to_path(waypoints)
+            }
+            Connectivity::Eight => {
+                let mut path: Vec<Point> = Vec::new();
+                let mut waypoints = waypoints.into_iter();
+                let mut current = waypoints.next().unwrap();
+                path.push(current);
+                for next in waypoints {
+                    while current != next {
+                        let mut delta = current.dir_obj(&next);
+                        if delta.diagonal() && !self.can_step(&current, delta) {
+                            delta = if self.can_move_to(current + delta.x_dir()) {
+                                delta.x_dir()
+                            } else {
+                                delta.y_dir()
+                            };
+                        }
+                        current = current + delta;
+                        path.push(current);
+                    }
+                }
+                path
+            }
+            Connectivity::Four => {
+                let mut path: Vec<Point> = Vec::new();
+                let mut waypoints = waypoints.into_iter();
+                let mut current = waypoints.next().unwrap();
+                path.push(current);
+                for next in waypoints {
+                    while current != next {
+                        let delta = next - current;
+                        current = if delta.x.abs() >= delta.y.abs() {
+                            current + Point::new(delta.x.signum(), 0)
+                        } else {
+                            current + Point::new(0, delta.y.signum())
+                        };
+                        path.push(current);
+                    }
+                }
+                path
+            }
+        }
+    }
+    fn neighborhood(&self, point: &Point) -> Vec<Point> {
+        match self.connectivity {
+            Connectivity::Eight => point.moore_neighborhood(),
+            Connectivity::Four => point.neumann_neighborhood(),
+        }
+    }
     fn get_neighbours(&self, point: Point) -> Vec<Point> {
-        point
-            .moore_neighborhood()
+        self.neighborhood(&point)
             .into_iter()
-            .filter(|p| self.can_move_to(*p))
+            .filter(|p| self.can_step(&point, point.dir_obj(p)))
             .collect::<Vec<Point>>()
     }
+    /// Checks whether a diagonal move in the given direction passes between the orthogonal
+    /// neighbours of node as required by the [DiagonalPolicy].
+    fn diagonal_allowed(&self, node: &Point, dir_num: i32) -> bool {
+        match self.diagonal_policy {
+            DiagonalPolicy::Always => true,
+            DiagonalPolicy::OneSideFree => {
+                self.indexed_neighbor(node, dir_num - 1) || self.indexed_neighbor(node, dir_num + 1)
+            }
+            DiagonalPolicy::BothSidesFree => {
+                self.indexed_neighbor(node, dir_num - 1) && self.indexed_neighbor(node, dir_num + 1)
+            }
+        }
+    }
+    /// Checks whether a single move from node in the given direction is possible. Diagonal moves
+    /// are only possible on 8-connected grids.
+    fn can_step(&self, node: &Point, dir: Direction) -> bool {
+        self.can_move_to(*node + dir)
+            && (!dir.diagonal()
+                || self.connectivity == Connectivity::Eight
+                    && self.diagonal_allowed(node, dir.num()))
+    }
     fn can_move_to(&self, pos: Point) -> bool {
         self.in_bounds(pos.x, pos.y) && !self.grid.get(pos.x as usize, pos.y as usize)
     }
@@ -82,20 +435,76 @@ impl PathingGrid {
     fn indexed_neighbor(&self, node: &Point, index: i32) -> bool {
         (self.neighbours.get_point(*node) & 1 << (index.rem_euclid(8))) != 0
     }
+    /// For a straight move onto node, checks whether the sides to the right and left of node
+    /// are free while the cells diagonally behind them are blocked, i.e. whether the sides open up.
+    fn opened_sides(&self, dir_num: i32, node: &Point) -> (bool, bool) {
+        (
+            self.indexed_neighbor(node, 2 + dir_num) && !self.indexed_neighbor(node, 3 + dir_num),
+            self.indexed_neighbor(node, 6 + dir_num) && !self.indexed_neighbor(node, 5 + dir_num),
+        )
+    }
     fn is_forced(&self, dir: Direction, node: &Point) -> bool {
         let dir_num = dir.num();
+        // On a 4-connected grid only horizontal moves have forced neighbours. Without corner
+        // cutting, diagonal moves have none. In both cases straight moves only have them where a
+        // side opens up.
+        if self.connectivity == Connectivity::Four
+            || self.diagonal_policy == DiagonalPolicy::BothSidesFree
+        {
+            let straight = match self.connectivity {
+                Connectivity::Four => Self::horizontal(dir),
+                Connectivity::Eight => !dir.diagonal(),
+            };
+            let (right, left) = self.opened_sides(dir_num, node);
+            return straight && (right || left);
+        }
         if dir.diagonal() {
             !self.indexed_neighbor(node, 3 + dir_num) || !self.indexed_neighbor(node, 5 + dir_num)
         } else {
             !self.indexed_neighbor(node, 2 + dir_num) || !self.indexed_neighbor(node, dir_num + 6)
         }
     }
+    /// Whether a direction is horizontal. On a 4-connected grid, vertical moves take the role of
+    /// diagonal moves on an 8-connected grid and scan horizontally at every step.
+    fn horizontal(dir: Direction) -> bool {
+        dir == Direction::EAST || dir == Direction::WEST
+    }
     fn pruned_neighborhood(&self, dir: Direction, node: &Point) -> (Vec<(Point, i32)>, bool) {
         let dir_num = dir.num();
         let mut n_mask: u8;
         let neighbours = self.neighbours.get_point(*node);
         let mut forced = false;
-        if dir.diagonal() {
+        if self.connectivity == Connectivity::Four {
+            if Self::horizontal(dir) {
+                n_mask = 1 << dir_num;
+                let (right, left) = self.opened_sides(dir_num, node);
+                if right {
+                    n_mask |= 1 << ((dir_num + 2) % 8);
+                    forced = true;
+                }
+                if left {
+                    n_mask |= 1 << ((dir_num + 6) % 8);
+                    forced = true;
+                }
+            } else {
+                n_mask = 0b0100_0101_u8.rotate_left(dir_num as u32);
+            }
+        } else if self.diagonal_policy == DiagonalPolicy::BothSidesFree {
+            if dir.diagonal() {
+                n_mask = 131_u8.rotate_left(dir_num as u32);
+            } else {
+                n_mask = 1 << dir_num;
+                let (right, left) = self.opened_sides(dir_num, node);
+                if right {
+                    n_mask |= 0b0000_0110_u8.rotate_left(dir_num as u32);
+                    forced = true;
+                }
+                if left {
+                    n_mask |= 0b1100_0000_u8.rotate_left(dir_num as u32);
+                    forced = true;
+                }
+            }
+        } else if dir.diagonal() {
             n_mask = 131_u8.rotate_left(dir_num as u32);
             if !self.indexed_neighbor(node, 3 + dir_num) {
                 n_mask |= 1 << ((dir_num + 2) % 8);
@@ -119,55 +528,89 @@ impl PathingGrid {
         let comb_mask = neighbours & n_mask;
         (
             (0..8)
-                .filter(|x| comb_mask & (1 << *x) != 0)
-                .map(|d| (node.moore_neighbor(d), 1))
+                .filter(|x| {
+                    comb_mask & (1 << *x) != 0 && (x % 2 == 0 || self.diagonal_allowed(node, *x))
+                })
+                .map(|d| {
+                    let neighbour = node.moore_neighbor(d);
+                    (neighbour, self.step_cost(node.dir_obj(&neighbour)))
+                })
                 .collect::<Vec<(Point, i32)>>(),
             forced,
         )
     }
 
+    /// Jumps from initial in the given direction until reaching the goal or a jump point. Every
+    /// step counts as a call in jump_calls, as do the jumps scanning away from each step. Steps
+    /// are taken in a loop, so the stack depth does not grow with the length of the jump. If the
+    /// search has a single target, which goal then only holds for, straight jumps locate it
+    /// without checking every cell.
     fn jump<F>(
         &self,
         initial: &Point,
         cost: i32,
         direction: Direction,
         goal: &F,
+        target: Option<&Point>,
+        jump_calls: &mut usize,
     ) -> Option<(Point, i32)>
         where
             F: Fn(&Point) -> bool,
     {
-        let new_n = *initial + direction;
-        if !self.can_move_to(new_n) {
-            return None;
-        }
+        let mut node = *initial;
+        let mut cost = cost;
+        loop {
+            *jump_calls += 1;
+            if self.jumps_straight(direction) {
+                return self.jump_straight(&node, cost, direction, goal, target);
+            }
+            if !self.can_step(&node, direction) {
+                return None;
+            }
+            let new_n = node + direction;
 
-        if goal(&new_n) {
-            return Some((new_n, cost));
-        }
-        if self.is_forced(direction, &new_n) {
-            return Some((new_n, cost));
-        }
-        if direction.diagonal()
-            && (self.jump(&new_n, 1, direction.x_dir(), goal).is_some()
-            || self.jump(&new_n, 1, direction.y_dir(), goal).is_some())
-        {
-            return Some((new_n, cost));
+            if goal(&new_n) {
+                return Some((new_n, cost));
+            }
+            if self.is_forced(direction, &new_n) {
+                return Some((new_n, cost));
+            }
+            if self.connectivity == Connectivity::Four
+                && !Self::horizontal(direction)
+                && (self.jump(&new_n, 1, Direction::EAST, goal, target, jump_calls).is_some()
+                    || self.jump(&new_n, 1, Direction::WEST, goal, target, jump_calls).is_some())
+            {
+                return Some((new_n, cost));
+            }
+            if direction.diagonal()
+                && (self.jump(&new_n, 1, direction.x_dir(), goal, target, jump_calls).is_some()
+                || self.jump(&new_n, 1, direction.y_dir(), goal, target, jump_calls).is_some())
+            {
+                return Some((new_n, cost));
+            }
+            node = new_n;
+            cost += self.step_cost(direction);
         }
-        self.jump(&new_n, cost + 1, direction, goal)
     }
     fn pathfinding_neighborhood(&self, pos: &Point) -> Vec<(Point, i32)> {
-        pos.moore_neighborhood()
+        self.neighborhood(pos)
             .into_iter()
-            .filter(|&position| self.can_move_to(position))
-            .map(|p| (p, 1))
+            .filter(|&position| self.can_step(pos, pos.dir_obj(&position)))
+            .map(|p| (p, self.step_cost(pos.dir_obj(&p))))
             .collect::<Vec<_>>()
     }
+    /// Computes the neighbour mask of point from the obstacles, with a bit set for every free
+    /// neighbour.
+    pub(crate) fn neighbour_mask(&self, point: &Point) -> u8 {
+        (0..8)
+            .filter(|&i| self.can_move_to(point.moore_neighbor(i)))
+            .fold(0, |mask, i| mask | 1 << i)
+    }
     fn update_neighbours(&mut self, x: i32, y: i32, blocked: bool) {
         let p = Point::new(x, y);
         for i in 0..8 {
             let neighbor = p.moore_neighbor(i);
             if self.in_bounds(neighbor.x, neighbor.y) {
-                info!("Yes");
                 let ix = (i + 4) % 8;
                 let mut n_mask = self.neighbours.get_point(neighbor);
                 if blocked {
@@ -179,41 +622,120 @@ impl PathingGrid {
             }
         }
     }
+    /// Generates the jump point successors of node, using improved pruning.
     pub fn jps_neighbours<F>(&self, parent: Option<&Point>, node: &Point, goal: &F) -> Vec<(Point, i32)>
         where
             F: Fn(&Point) -> bool,
     {
-        match parent {
-            Some(parent_node) => {
-                let mut succ = vec![];
-                let dir = parent_node.dir_obj(node);
-                for (n, c) in &self.pruned_neighborhood(dir, node).0 {
-                    let dir = node.dir_obj(n);
-                    if let Some((jumped_node, cost)) = self.jump(node, *c, dir, goal) {
-                        let neighbour_dir = node.dir_obj(&jumped_node);
-                        if IMPROVED_PRUNING
-                            && dir.diagonal()
-                            && !self.is_forced(neighbour_dir, &jumped_node)
-                        {
-                            let mut jump_points =
-                                self.jps_neighbours(Some(parent_node), &jumped_node, goal);
-                            succ.append(&mut jump_points);
-                        }
-                        {
-                            succ.push((jumped_node, cost));
-                        }
-                    }
+        self.jps_successors(parent, node, goal, None, true, &mut 0)
+    }
+    /// Jumps using the [JumpTable] if it is enabled and the search has a single target, which goal
+    /// then only holds for. Without a table, the target still spares straight jumps from checking
+    /// the goal at every cell.
+    fn jump_towards<F>(
+        &self,
+        initial: &Point,
+        cost: i32,
+        direction: Direction,
+        goal: &F,
+        target: Option<&Point>,
+        jump_calls: &mut usize,
+    ) -> Option<(Point, i32)>
+    where
+        F: Fn(&Point) -> bool,
+    {
+        match (&self.jump_table, target) {
+            (Some(table), Some(target)) => {
+                *jump_calls += 1;
+                self.table_jump(table, initial, cost, direction, target)
+            }
+            _ => self.jump(initial, cost, direction, goal, target, jump_calls),
+        }
+    }
+    /// Generates the jump point successors of node. With improved pruning, diagonal jump points
+    /// without forced neighbours are expanded immediately, their successors preceding them. These
+    /// expansions can chain, so they are kept on an explicit stack rather than recursing.
+    fn jps_successors<F>(
+        &self,
+        parent: Option<&Point>,
+        node: &Point,
+        goal: &F,
+        target: Option<&Point>,
+        improved_pruning: bool,
+        jump_calls: &mut usize,
+    ) -> Vec<(Point, i32)>
+    where
+        F: Fn(&Point) -> bool,
+    {
+        /// A node being expanded, with the cost of reaching it from the node whose successors are
+        /// generated and the jump point to add once its own successors are added.
+        struct Expansion {
+            node: Point,
+            offset: i32,
+            neighbours: std::vec::IntoIter<(Point, i32)>,
+            jump_point: Option<(Point, i32)>,
+        }
+        let parent_node = match parent {
+            Some(parent_node) => parent_node,
+            None => return self.pathfinding_neighborhood(node),
+        };
+        let mut succ = vec![];
+        let mut stack = vec![Expansion {
+            node: *node,
+            offset: 0,
+            neighbours: self
+                .pruned_neighborhood(parent_node.dir_obj(node), node)
+                .0
+                .into_iter(),
+            jump_point: None,
+        }];
+        while let Some(expansion) = stack.last_mut() {
+            let node = expansion.node;
+            let offset = expansion.offset;
+            let Some((n, c)) = expansion.neighbours.next() else {
+                succ.extend(expansion.jump_point);
+                stack.pop();
+                continue;
+            };
+            let dir = node.dir_obj(&n);
+            if let Some((jumped_node, cost)) =
+                self.jump_towards(&node, c, dir, goal, target, jump_calls)
+            {
+                // The successors of the jump point are reached through it, so the cost of getting
+                // there is included.
+                let cost = offset + cost;
+                let neighbour_dir = node.dir_obj(&jumped_node);
+                // The immediate expansion infers the direction of its successors from the parent,
+                // which misses the forced neighbours particular to BothSidesFree, so it is
+                // limited to the other policies.
+                if improved_pruning
+                    && self.diagonal_policy != DiagonalPolicy::BothSidesFree
+                    && dir.diagonal()
+                    && !self.is_forced(neighbour_dir, &jumped_node)
+                {
+                    stack.push(Expansion {
+                        node: jumped_node,
+                        offset: cost,
+                        neighbours: self
+                            .pruned_neighborhood(neighbour_dir, &jumped_node)
+                            .0
+                            .into_iter(),
+                        jump_point: Some((jumped_node, cost)),
+                    });
+                } else {
+                    succ.push((jumped_node, cost));
                 }
-                succ
             }
-            None => self.pathfinding_neighborhood(node),
         }
+        succ
     }
-    /// Retrieves the component id a given [Point] belongs to.
+    /// Retrieves the component id a given [Point] belongs to, which is
+    /// [NO_COMPONENT](components::NO_COMPONENT) for blocked points.
     pub fn get_component(&self, point: &Point) -> usize {
-        self.components.find(self.get_ix_point(point))
+        self.components.label(self.get_ix_point(point))
     }
-    /// Checks if start and goal are on the same component.
+    /// Checks if start and goal are on the same component. The answer may be stale if the
+    /// [components are dirty](Self::components_dirty).
     pub fn unreachable(&self, start: &Point, goal: &Point) -> bool {
         if self.in_bounds(start.x, start.y) && self.in_bounds(goal.x, goal.y) {
             let start_ix = self.get_ix_point(start);
@@ -232,25 +754,56 @@ impl PathingGrid {
     pub fn neighbours_unreachable(&self, start: &Point, goal: &Point) -> bool {
         if self.in_bounds(start.x, start.y) && self.in_bounds(goal.x, goal.y) {
             let start_ix = self.get_ix_point(start);
-            !goal.moore_neighborhood().iter().any(|p| {
+            !self.neighborhood(goal).iter().any(|p| {
                 self.in_bounds(p.x, p.y) && self.components.equiv(start_ix, self.get_ix_point(p))
             })
         } else {
             true
         }
     }
-    /// Computes a path from start to goal using JPS. If approximate is [true], then it will
-    /// path to one of the neighbours of the goal, which is useful if goal itself is
-    /// blocked. The heuristic used is the
-    /// [Chebyshev distance](https://en.wikipedia.org/wiki/Chebyshev_distance).
+    /// Checks if no point within the given move distance of the goal is on the same component as
+    /// the start.
+    pub fn region_unreachable(&self, start: &Point, goal: &Point, radius: i32) -> bool {
+        if radius == 0 {
+            return self.unreachable(start, goal);
+        }
+        if self.in_bounds(start.x, start.y) {
+            let start_ix = self.get_ix_point(start);
+            !(goal.x - radius..=goal.x + radius)
+                .cartesian_product(goal.y - radius..=goal.y + radius)
+                .map(|(x, y)| Point::new(x, y))
+                .any(|p| {
+                    self.move_distance(&p, goal) <= radius
+                        && self.in_bounds(p.x, p.y)
+                        && self.components.equiv(start_ix, self.get_ix_point(&p))
+                })
+        } else {
+            true
+        }
+    }
+    /// Checks that a search can start from the given point.
+    pub fn check_start(&self, start: &Point) -> Result<(), PathError> {
+        if !self.in_bounds(start.x, start.y) {
+            Err(PathError::StartOutOfBounds(*start))
+        } else if self.grid.get_point(*start) {
+            Err(PathError::StartBlocked(*start))
+        } else {
+            Ok(())
+        }
+    }
+    /// Computes a path from start to goal using JPS. If the
+    /// [goal tolerance](SearchConfig::goal_tolerance) is positive, then it will path to a point
+    /// within that move distance of the goal, which is useful if goal itself is blocked. The
+    /// heuristic used is the [Chebyshev distance](https://en.wikipedia.org/wiki/Chebyshev_distance),
+    /// or its counterpart under the [Connectivity] and [CostModel] of the grid.
     pub fn get_path_single_goal(
         &self,
         start: Point,
         goal: Point,
-        approximate: bool,
-    ) -> Option<Vec<Point>> {
-        self.get_waypoints_single_goal(start, goal, approximate)
-            .map(waypoints_to_path)
+        config: &SearchConfig,
+    ) -> Result<Vec<Point>, PathError> {
+        self.get_waypoints_single_goal(start, goal, config)
+            .map(|waypoints| self.waypoints_to_path(waypoints))
     }
 
     /// Computes a path from start to one of the given goals. This is done by taking the
@@ -260,72 +813,155 @@ impl PathingGrid {
         &self,
         start: Point,
         goals: Vec<&Point>,
-    ) -> Option<(Point, Vec<Point>)> {
-        self.get_waypoints_multiple_goals(start, goals)
-            .map(|(x, y)| (x, waypoints_to_path(y)))
+        config: &SearchConfig,
+    ) -> Result<(Point, Vec<Point>), PathError> {
+        self.get_waypoints_multiple_goals(start, goals, config)
+            .map(|(x, y)| (x, self.waypoints_to_path(y)))
     }
     /// The raw waypoints (jump points) from which [get_path_multiple_goals](Self::get_path_multiple_goals) makes a path.
     pub fn get_waypoints_multiple_goals(
         &self,
         start: Point,
         goals: Vec<&Point>,
-    ) -> Option<(Point, Vec<Point>)> {
+        config: &SearchConfig,
+    ) -> Result<(Point, Vec<Point>), PathError> {
+        self.get_path_result_multiple_goals(start, goals, config)
+            .map(|result| (result.goal, result.waypoints))
+    }
+    /// Like [get_waypoints_multiple_goals](Self::get_waypoints_multiple_goals), but returns a
+    /// [PathResult] which also holds the cost of the path and search statistics. Goals in a
+    /// different component than the start are skipped.
+    pub fn get_path_result_multiple_goals(
+        &self,
+        start: Point,
+        goals: Vec<&Point>,
+        config: &SearchConfig,
+    ) -> Result<PathResult, PathError> {
+        self.check_start(&start)?;
         if goals.is_empty() {
-            return None;
+            return Err(PathError::NoGoals);
         }
-        let result = astar_jps(
-            &start,
-            |&parent, node| {
-                self.jps_neighbours(parent, node, &|node_pos| goals.contains(&node_pos))
-            },
-            |&point| {
-                (goals.iter().map(|x| point.move_distance(x)).min().unwrap() as f32
-                    * HEURISTIC_FACTOR) as i32
-            },
-            |node_pos| goals.contains(&node_pos),
-        ).map(|(v, _c)| v.collect_vec());
-        result.map(|v| (*v.last().unwrap(), v))
+        let goals = goals
+            .into_iter()
+            .filter(|goal| !self.region_unreachable(&start, goal, config.goal_tolerance))
+            .copied()
+            .collect_vec();
+        if goals.is_empty() {
+            info!("No goal is reachable from {}", start);
+            return Err(self.unreachable_error());
+        }
+        self.find_waypoints(start, &goals, config)
     }
     /// The raw waypoints (jump points) from which [get_path_single_goal](Self::get_path_single_goal) makes a path.
     pub fn get_waypoints_single_goal(
         &self,
         start: Point,
         goal: Point,
-        approximate: bool,
-    ) -> Option<Vec<Point>> {
-        if approximate {
-            if self.neighbours_unreachable(&start, &goal) {
-                info!("No neighbours of {} are reachable from {}", goal, start);
-                return None;
-            }
-            info!(
-                "Neighborhood of {} is reachable from {}, computing path",
-                goal, start
-            );
-            astar_jps(
-                &start,
-                |&parent, node| {
-                    self.jps_neighbours(parent, node, &|node_pos| {
-                        node_pos.move_distance(&goal) <= 1
-                    })
-                },
-                |&point| (point.move_distance(&goal) as f32 * HEURISTIC_FACTOR) as i32,
-                |node_pos| node_pos.move_distance(&goal) <= 1,
-            ).map(|(v, _c)| v.collect_vec())
+        config: &SearchConfig,
+    ) -> Result<Vec<Point>, PathError> {
+        self.get_path_result_single_goal(start, goal, config)
+            .map(|result| result.waypoints)
+    }
+    /// Like [get_waypoints_single_goal](Self::get_waypoints_single_goal), but returns a
+    /// [PathResult] which also holds the cost of the path and search statistics.
+    pub fn get_path_result_single_goal(
+        &self,
+        start: Point,
+        goal: Point,
+        config: &SearchConfig,
+    ) -> Result<PathResult, PathError> {
+        self.check_start(&start)?;
+        if self.region_unreachable(&start, &goal, config.goal_tolerance) {
+            info!("{} is not reachable from {}", goal, start);
+            return Err(self.unreachable_error());
+        }
+        info!("{} is reachable from {}, computing path", goal, start);
+        self.find_waypoints(start, &[goal], config)
+    }
+    /// The error for goals which cannot be reached, which can not be relied on if the components
+    /// are dirty.
+    pub(crate) fn unreachable_error(&self) -> PathError {
+        if self.components_dirty {
+            PathError::ComponentsDirty
         } else {
-            if self.unreachable(&start, &goal) {
-                info!("{} is not reachable from {}", start, goal);
-                return None;
-            }
-            info!("{} is reachable from {}, computing path", goal, start);
-            astar_jps(
-                &start,
-                |&parent, node| self.jps_neighbours(parent, node, &|node_pos| *node_pos == goal),
-                |&point| (point.move_distance(&goal) as f32 * HEURISTIC_FACTOR) as i32,
-                |node_pos| *node_pos == goal,
-            ).map(|(v, _c)| v.collect_vec())
+            PathError::GoalUnreachable
+        }
+    }
+    /// Determines why a search which passed the component checks did not find a path.
+    pub(crate) fn search_failure(&self, config: &SearchConfig, stats: &SearchStats) -> PathError {
+        if config
+            .max_expansions
+            .is_some_and(|max| stats.expanded >= max)
+        {
+            PathError::BudgetExhausted
+        } else {
+            self.unreachable_error()
         }
     }
+    /// Runs JPS from start until it reaches a point within the goal tolerance of any of the goals.
+    fn find_waypoints(
+        &self,
+        start: Point,
+        goals: &[Point],
+        config: &SearchConfig,
+    ) -> Result<PathResult, PathError> {
+        let tolerance = config.goal_tolerance;
+        let reached = |node: &Point| {
+            goals
+                .iter()
+                .any(|goal| self.move_distance(node, goal) <= tolerance)
+        };
+        // The jump table can only stop jumps at a single point.
+        let target = match goals {
+            [goal] if tolerance == 0 => Some(goal),
+            _ => None,
+        };
+        let mut stats = SearchStats::default();
+        let mut jump_calls = 0;
+        let mut expanded = Vec::new();
+        let result = astar_jps_with_config(
+            &start,
+            |&parent, node| {
+                if config.record_expanded {
+                    expanded.push(*node);
+                }
+                self.jps_successors(
+                    parent,
+                    node,
+                    &reached,
+                    target,
+                    config.improved_pruning,
+                    &mut jump_calls,
+                )
+            },
+            |point| {
+                goals
+                    .iter()
+                    .map(|goal| self.heuristic(point, goal, tolerance, config.heuristic_weight))
+                    .min()
+                    .unwrap()
+            },
+            reached,
+            config,
+            &mut stats,
+        )
+        .map(|(v, c)| (v.collect_vec(), c));
+        stats.jump_calls = jump_calls;
+        let (mut waypoints, cost) = result.ok_or_else(|| self.search_failure(config, &stats))?;
+        waypoints.reverse();
+        let last = waypoints.last().unwrap();
+        let goal = *goals
+            .iter()
+            .find(|goal| self.move_distance(last, goal) <= tolerance)
+            .unwrap();
+        Ok(PathResult {
+            waypoints,
+            cost,
+            goal,
+            stats,
+            expanded,
+        })
+    }
     /// Regenerates the components if they are marked as dirty.
     pub fn update(&mut self) {
         if self.components_dirty {
@@ -333,51 +969,176 @@ impl PathingGrid {
             self.generate_components();
         }
     }
-    /// Generates a new [UnionFind] structure and links up grid neighbours to the same components.
-    pub fn generate_components(&mut self) {
-        info!("Generating connected components");
+    /// Indices of the cells which can be reached from the cell at index ix in a single move.
+    fn neighbour_ixs(&self, ix: usize) -> Vec<usize> {
         let w = self.grid.width;
-        let h = self.grid.height;
-        self.components = UnionFind::new(w * h);
-        self.components_dirty = false;
+        let point = Point::new((ix % w) as i32, (ix / w) as i32);
+        self.get_neighbours(point)
+            .into_iter()
+            .map(|p| self.get_ix_point(&p))
+            .collect()
+    }
+    /// Updates a batch of positions on the grid, with later updates to the same position taking
+    /// precedence. Positions outside of the grid are ignored. The components are then fixed up
+    /// once for the whole batch, only visiting the cells around the changes: components around
+    /// blocked cells are split by searching from their neighbours in lockstep until the searches
+    /// meet, after which the components around freed cells are joined. While the components are
+    /// dirty they are left alone until they are regenerated.
+    pub fn set_many<I>(&mut self, cells: I)
+    where
+        I: IntoIterator<Item = (Point, bool)>,
+    {
+        let mut changes = FxHashMap::default();
+        for (point, blocked) in cells {
+            if self.point_in_bounds(point) {
+                changes.insert(self.get_ix_point(&point), blocked);
+            }
+        }
+        let w = self.grid.width;
+        let (blocked, freed): (Vec<_>, Vec<_>) = changes
+            .into_iter()
+            .filter(|&(ix, blocked)| self.grid.get(ix % w, ix / w) != blocked)
+            .partition(|&(_, blocked)| blocked);
+        // Blocking cells first only ever splits components, after which freeing cells only ever
+        // joins them.
+        for &(ix, _) in &blocked {
+            self.update_cell(ix, true);
+        }
+        if !self.components_dirty && !blocked.is_empty() {
+            let cells = blocked.iter().map(|&(ix, _)| ix).collect::<Vec<_>>();
+            let seeds = cells
+                .iter()
+                .flat_map(|&ix| {
+                    self.neighborhood(&Point::new((ix % w) as i32, (ix / w) as i32))
+                })
+                .filter(|p| self.can_move_to(*p))
+                .map(|p| self.get_ix_point(&p))
+                .collect::<Vec<_>>();
+            let mut components = std::mem::take(&mut self.components);
+            components.block_many(&cells, &seeds, |ix| self.neighbour_ixs(ix));
+            self.components = components;
+        }
+        for &(ix, _) in &freed {
+            self.update_cell(ix, false);
+        }
+        if !self.components_dirty {
+            let mut components = std::mem::take(&mut self.components);
+            for &(ix, _) in &freed {
+                components.unblock(ix, |ix| self.neighbour_ixs(ix));
+            }
+            self.components = components;
+        }
+        let changed = blocked
+            .iter()
+            .chain(&freed)
+            .map(|&(ix, _)| Point::new((ix % w) as i32, (ix / w) as i32))
+            .collect::<Vec<_>>();
+        self.repair_jump_table(&changed);
+    }
+    fn update_cell(&mut self, ix: usize, blocked: bool) {
+        let w = self.grid.width;
+        let (x, y) = (ix % w, ix / w);
+        self.update_neighbours(x as i32, y as i32, blocked);
+        self.grid.set(x, y, blocked);
+        self.transposed.set(y, x, blocked);
+    }
+    /// Blocks all cells in the rectangle, like [set_rectangle](Grid::set_rectangle).
+    pub fn fill_rect(&mut self, rect: &grid_util::Rect) {
+        self.set_rectangle(rect, true);
+    }
+    /// Frees all cells in the rectangle, like [set_rectangle](Grid::set_rectangle).
+    pub fn clear_rect(&mut self, rect: &grid_util::Rect) {
+        self.set_rectangle(rect, false);
+    }
+    /// Sets the cells covered by the mask placed with its origin at offset to blocked, leaving the
+    /// other cells unchanged. Stamping with blocked set to [false] clears the shape again.
+    pub fn stamp(&mut self, mask: &BoolGrid, offset: Point, blocked: bool) {
+        self.set_many(
+            (0..mask.width)
+                .cartesian_product(0..mask.height)
+                .filter(|&(x, y)| mask.get(x, y))
+                .map(|(x, y)| (offset + Point::new(x as i32, y as i32), blocked)),
+        );
+    }
+    /// Builds a grid of the given size in which the cells for which blocked returns [true] are
+    /// occupied. Unlike setting cells one by one, this computes the neighbours and components in
+    /// a single pass over the grid.
+    pub fn from_fn<F>(width: usize, height: usize, mut blocked: F) -> PathingGrid
+    where
+        F: FnMut(usize, usize) -> bool,
+    {
+        let mut grid = BoolGrid::new(width, height, false);
+        for y in 0..height {
+            for x in 0..width {
+                if blocked(x, y) {
+                    grid.set(x, y, true);
+                }
+            }
+        }
+        PathingGrid::from_parts(
+            grid,
+            Connectivity::default(),
+            DiagonalPolicy::default(),
+            CostModel::default(),
+        )
+    }
+    /// Builds a grid of the given size from the occupancy of its cells in row-major order, see
+    /// [from_fn](Self::from_fn).
+    pub fn from_slice(width: usize, height: usize, blocked: &[bool]) -> PathingGrid {
+        assert_eq!(
+            blocked.len(),
+            width * height,
+            "expected {} cells for a {}x{} grid",
+            width * height,
+            width,
+            height
+        );
+        PathingGrid::from_fn(width, height, |x, y| blocked[x + y * width])
+    }
+    /// Builds a grid on top of the given occupancy with the given settings, computing the
+    /// neighbours and components from scratch.
+    pub(crate) fn from_parts(
+        grid: BoolGrid,
+        connectivity: Connectivity,
+        diagonal_policy: DiagonalPolicy,
+        cost_model: CostModel,
+    ) -> PathingGrid {
+        let (w, h) = (grid.width, grid.height);
+        let mut pathing_grid = PathingGrid {
+            transposed: bitscan::transpose(&grid),
+            grid,
+            neighbours: SimpleGrid::new(w, h, 0),
+            components: Components::new(w * h),
+            components_dirty: true,
+            connectivity,
+            diagonal_policy,
+            cost_model,
+            jump_table: None,
+        };
         for x in 0..w {
             for y in 0..h {
-                if !self.grid.get(x, y) {
-                    let parent_ix = self.grid.get_ix(x, y);
-                    let point = Point::new(x as i32, y as i32);
-
-                    [
-                        Point::new(point.x, point.y + 1),
-                        Point::new(point.x + 1, point.y),
-                        Point::new(point.x + 1, point.y + 1),
-                    ]
-                        .into_iter()
-                        .filter(|p| self.grid.point_in_bounds(*p) && !self.grid.get_point(*p))
-                        .map(|p| self.grid.get_ix(p.x as usize, p.y as usize))
-                        .for_each(|ix| { self.components.union(parent_ix, ix); });
-                }
+                let mask = pathing_grid.neighbour_mask(&Point::new(x as i32, y as i32));
+                pathing_grid.neighbours.set(x, y, mask);
             }
         }
+        pathing_grid.generate_components();
+        pathing_grid
+    }
+    /// Labels the components of the grid from scratch by flood-filling from every free cell.
+    pub fn generate_components(&mut self) {
+        info!("Generating connected components");
+        let w = self.grid.width;
+        let mut components = Components::new(w * self.grid.height);
+        components.generate(|ix| !self.grid.get(ix % w, ix / w), |ix| self.neighbour_ixs(ix));
+        self.components = components;
+        self.components_dirty = false;
     }
 }
 
 impl fmt::Display for PathingGrid {
+    /// Draws the grid as an ASCII picture, see [ascii].
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "Grid:")?;
-        for y in 0..self.grid.height {
-            let values = (0..self.grid.width)
-                .map(|x| self.grid.get(x, y) as i32)
-                .collect::<Vec<i32>>();
-            writeln!(f, "{:?}", values)?;
-        }
-        writeln!(f, "\nNeighbours:")?;
-        for y in 0..self.neighbours.height {
-            let values = (0..self.neighbours.width)
-                .map(|x| self.neighbours.get(x, y) as i32)
-                .collect::<Vec<i32>>();
-            writeln!(f, "{:?}", values)?;
-        }
-        Ok(())
+        write!(f, "{}", ascii::render(self, &ascii::Overlay::default()))
     }
 }
 
@@ -385,9 +1146,19 @@ impl Grid<bool> for PathingGrid {
     fn new(width: usize, height: usize, default_value: bool) -> Self {
         let mut base_grid = PathingGrid {
             grid: BoolGrid::new(width, height, default_value),
-            neighbours: SimpleGrid::new(width, height, 255),
-            components: UnionFind::new(width * height),
+            // Every neighbour of a cell shares the default value.
+            neighbours: SimpleGrid::new(width, height, if default_value { 0 } else { 255 }),
+            transposed: BoolGrid::new(height, width, default_value),
+            components: if default_value {
+                Components::new(width * height)
+            } else {
+                Components::single(width * height)
+            },
             components_dirty: false,
+            connectivity: Connectivity::default(),
+            diagonal_policy: DiagonalPolicy::default(),
+            cost_model: CostModel::default(),
+            jump_table: None,
         };
         // Emulates 'placing' of blocked tile around map border to correctly initialize neighbours
         // and make behaviour of a map bordered by tiles the same as a borderless map.
@@ -404,22 +1175,17 @@ impl Grid<bool> for PathingGrid {
     fn get(&self, x: usize, y: usize) -> bool {
         self.grid.get(x, y)
     }
-    /// Updates a position on the grid. Joins newly connected components and flags the components
-    /// as dirty if components are (potentially) broken apart into multiple.
+    /// Updates a position on the grid, see [set_many](PathingGrid::set_many).
     fn set(&mut self, x: usize, y: usize, blocked: bool) {
-        let p = Point::new(x as i32, y as i32);
-        if self.grid.get(x, y) != blocked && blocked {
-            self.components_dirty = true;
-        } else {
-            for p in self.get_neighbours(p) {
-                self.components.union(
-                    self.grid.get_ix(x, y),
-                    self.grid.get_ix(p.x as usize, p.y as usize),
-                );
-            }
-        }
-        self.update_neighbours(p.x, p.y, blocked);
-        self.grid.set(x, y, blocked);
+        self.set_many([(Point::new(x as i32, y as i32), blocked)]);
+    }
+    /// Sets a rectangle on the grid in a single batch, see [set_many](PathingGrid::set_many).
+    fn set_rectangle(&mut self, rect: &grid_util::Rect, blocked: bool) {
+        self.set_many(
+            (rect.x1..rect.x2)
+                .cartesian_product(rect.y1..rect.y2)
+                .map(|(x, y)| (Point::new(x, y), blocked)),
+        );
     }
     fn width(&self) -> usize {
         self.grid.width()
@@ -436,8 +1202,453 @@ mod tests {
     #[test]
     fn test_component_generation() {
         let mut path_graph = PathingGrid::new(3, 4, true);
-        path_graph.grid.set(1, 1, false);
+        path_graph.set(1, 1, false);
         path_graph.generate_components();
-        assert!(!path_graph.components.equiv(0, 4))
+        assert!(!path_graph.components().equiv(0, 4))
+    }
+
+    #[test]
+    fn test_incremental_components() {
+        let mut pathing_grid = PathingGrid::new(5, 5, false);
+        pathing_grid.generate_components();
+        let (left, right) = (Point::new(0, 2), Point::new(4, 2));
+        pathing_grid.set_rectangle(&grid_util::Rect::new(2, 0, 1, 5), true);
+        assert!(!pathing_grid.components_dirty());
+        assert!(pathing_grid.unreachable(&left, &right));
+        assert_eq!(pathing_grid.components().count(), 2);
+        pathing_grid.set(2, 4, false);
+        assert!(!pathing_grid.unreachable(&left, &right));
+        assert_eq!(pathing_grid.components().count(), 1);
+        assert_eq!(
+            pathing_grid.components().size(pathing_grid.get_component(&left)),
+            21
+        );
+    }
+
+    #[test]
+    fn test_components_without_generation() {
+        let mut pathing_grid = PathingGrid::new(4, 4, true);
+        pathing_grid.set(0, 0, false);
+        pathing_grid.set(1, 1, false);
+        assert!(!pathing_grid.components_dirty());
+        assert!(!pathing_grid.unreachable(&Point::new(0, 0), &Point::new(1, 1)));
+        pathing_grid.set_diagonal_policy(DiagonalPolicy::OneSideFree);
+        assert!(pathing_grid.components_dirty());
+        pathing_grid.update();
+        assert!(pathing_grid.unreachable(&Point::new(0, 0), &Point::new(1, 1)));
+    }
+
+    /// Opening a wall which spans several words of the grid reconnects both sides for searches.
+    #[test]
+    fn test_components_after_opening_wall() {
+        let mut pathing_grid = PathingGrid::new(130, 3, false);
+        pathing_grid.set_rectangle(&grid_util::Rect::new(100, 0, 1, 3), true);
+        let (start, goal) = (Point::new(0, 1), Point::new(129, 1));
+        let config = SearchConfig::default();
+        assert_eq!(
+            pathing_grid.get_waypoints_single_goal(start, goal, &config),
+            Err(PathError::GoalUnreachable)
+        );
+        pathing_grid.set(100, 1, false);
+        assert!(!pathing_grid.components_dirty());
+        assert!(pathing_grid
+            .get_waypoints_single_goal(start, goal, &config)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_bulk_construction() {
+        let blocked = [
+            false, true, false, false, //
+            false, true, false, true, //
+            false, false, true, false, //
+        ];
+        let bulk = PathingGrid::from_slice(4, 3, &blocked);
+        let mut incremental = PathingGrid::new(4, 3, false);
+        for (ix, &b) in blocked.iter().enumerate() {
+            incremental.set(ix % 4, ix / 4, b);
+        }
+        assert_eq!(bulk.to_string(), incremental.to_string());
+        assert_eq!(bulk.neighbours().values, incremental.neighbours().values);
+        for a in 0..12 {
+            for b in 0..12 {
+                assert_eq!(
+                    bulk.components().equiv(a, b),
+                    incremental.components().equiv(a, b)
+                );
+            }
+        }
+        let from_fn = PathingGrid::from_fn(4, 3, |x, y| blocked[x + 4 * y]);
+        assert_eq!(from_fn.components(), bulk.components());
+    }
+
+    #[test]
+    fn test_batch_edits() {
+        let mut pathing_grid = PathingGrid::new(6, 4, false);
+        let (left, right) = (Point::new(0, 0), Point::new(5, 3));
+        pathing_grid.fill_rect(&grid_util::Rect::new(2, 0, 2, 4));
+        assert!(pathing_grid.unreachable(&left, &right));
+        assert_eq!(pathing_grid.components().count(), 2);
+        pathing_grid.clear_rect(&grid_util::Rect::new(2, 1, 2, 2));
+        assert!(!pathing_grid.unreachable(&left, &right));
+        let mut mask = BoolGrid::new(2, 2, true);
+        mask.set(0, 1, false);
+        mask.set(1, 1, false);
+        pathing_grid.stamp(&mask, Point::new(2, 1), true);
+        assert_eq!(
+            pathing_grid.to_string(),
+            "..##..\n..##..\n......\n..##..\n"
+        );
+        assert!(!pathing_grid.unreachable(&left, &right));
+        pathing_grid.set_many([(Point::new(3, 2), true), (Point::new(9, 9), false)]);
+        assert!(pathing_grid.unreachable(&left, &right));
+        assert_eq!(pathing_grid.components().count(), 2);
+    }
+
+    /// Batched edits on a 4-connected grid split off cells which only touch diagonally.
+    #[test]
+    fn test_four_connected_batch_edits() {
+        let mut pathing_grid = PathingGrid::new(5, 5, false);
+        pathing_grid.set_connectivity(Connectivity::Four);
+        pathing_grid.update();
+        let (corner, far) = (Point::new(0, 0), Point::new(4, 4));
+        pathing_grid.set_many([(Point::new(1, 0), true), (Point::new(0, 1), true)]);
+        assert!(pathing_grid.unreachable(&corner, &far));
+        assert!(!pathing_grid.unreachable(&Point::new(1, 1), &far));
+        assert_eq!(pathing_grid.components().count(), 2);
+    }
+
+    /// Diagonally adjacent cells are only connected on an 8-connected grid.
+    #[test]
+    fn test_four_connected_components() {
+        let mut pathing_grid = PathingGrid::new(2, 2, false);
+        pathing_grid.set(1, 0, true);
+        pathing_grid.set(0, 1, true);
+        pathing_grid.set_connectivity(Connectivity::Four);
+        pathing_grid.update();
+        assert!(pathing_grid.unreachable(&Point::new(0, 0), &Point::new(1, 1)));
+    }
+
+    /// Two cells which only touch diagonally between two obstacles are only connected if corners
+    /// may be cut.
+    #[test]
+    fn test_diagonal_policy_components() {
+        let mut pathing_grid = PathingGrid::new(2, 2, false);
+        pathing_grid.set(1, 0, true);
+        pathing_grid.set(0, 1, true);
+        pathing_grid.generate_components();
+        let start = Point::new(0, 0);
+        let goal = Point::new(1, 1);
+        assert!(!pathing_grid.unreachable(&start, &goal));
+        for policy in [DiagonalPolicy::OneSideFree, DiagonalPolicy::BothSidesFree] {
+            pathing_grid.set_diagonal_policy(policy);
+            pathing_grid.update();
+            assert!(pathing_grid.unreachable(&start, &goal));
+        }
+    }
+
+    #[test]
+    fn test_no_corner_cutting_path() {
+        let mut pathing_grid = PathingGrid::new(3, 3, false);
+        pathing_grid.set_diagonal_policy(DiagonalPolicy::BothSidesFree);
+        pathing_grid.set(1, 1, true);
+        pathing_grid.generate_components();
+        let path = pathing_grid
+            .get_path_single_goal(Point::new(0, 0), Point::new(2, 2), &SearchConfig::default())
+            .unwrap();
+        assert_eq!(path.len(), 5);
+        assert!(path.windows(2).all(|w| w[0].manhattan_distance(&w[1]) == 1));
+    }
+
+    #[test]
+    fn test_octile_cost() {
+        let mut pathing_grid = PathingGrid::new(5, 5, false);
+        pathing_grid.set_cost_model(CostModel::Octile);
+        pathing_grid.set(2, 1, true);
+        pathing_grid.generate_components();
+        let result = pathing_grid
+            .get_path_result_single_goal(
+                Point::new(0, 0),
+                Point::new(4, 2),
+                &SearchConfig::optimal(),
+            )
+            .unwrap();
+        assert_eq!(result.cost, 2 * OCTILE_DIAGONAL_COST + 2 * OCTILE_STRAIGHT_COST);
+        assert_eq!(result.waypoints.first(), Some(&Point::new(0, 0)));
+        assert_eq!(result.waypoints.last(), Some(&Point::new(4, 2)));
+        assert!(result.stats.expanded > 0);
+        assert!(result.stats.generated >= result.stats.expanded);
+        assert!(result.stats.jump_calls > 0);
+    }
+
+    #[test]
+    fn test_goal_tolerance() {
+        let mut pathing_grid = PathingGrid::new(5, 5, false);
+        pathing_grid.set(4, 4, true);
+        pathing_grid.generate_components();
+        let start = Point::new(0, 0);
+        let goal = Point::new(4, 4);
+        let config = SearchConfig::default();
+        assert_eq!(
+            pathing_grid.get_path_single_goal(start, goal, &config),
+            Err(PathError::GoalUnreachable)
+        );
+        let config = SearchConfig {
+            goal_tolerance: 2,
+            ..SearchConfig::optimal()
+        };
+        let path = pathing_grid.get_path_single_goal(start, goal, &config).unwrap();
+        assert_eq!(path.last(), Some(&Point::new(2, 2)));
+    }
+
+    #[test]
+    fn test_max_expansions() {
+        let mut pathing_grid = PathingGrid::new(10, 10, false);
+        pathing_grid.set_rectangle(&grid_util::Rect::new(2, 0, 1, 9), true);
+        pathing_grid.generate_components();
+        let start = Point::new(0, 0);
+        let goal = Point::new(4, 0);
+        let config = SearchConfig {
+            max_expansions: Some(1),
+            ..SearchConfig::default()
+        };
+        assert_eq!(
+            pathing_grid.get_path_single_goal(start, goal, &config),
+            Err(PathError::BudgetExhausted)
+        );
+        let config = SearchConfig::default();
+        assert!(pathing_grid.get_path_single_goal(start, goal, &config).is_ok());
+    }
+
+    #[test]
+    fn test_path_errors() {
+        let mut pathing_grid = PathingGrid::new(5, 5, false);
+        pathing_grid.set(1, 1, true);
+        pathing_grid.generate_components();
+        let config = SearchConfig::default();
+        let goal = Point::new(4, 4);
+        assert_eq!(
+            pathing_grid.get_path_single_goal(Point::new(-1, 0), goal, &config),
+            Err(PathError::StartOutOfBounds(Point::new(-1, 0)))
+        );
+        assert_eq!(
+            pathing_grid.get_path_single_goal(Point::new(1, 1), goal, &config),
+            Err(PathError::StartBlocked(Point::new(1, 1)))
+        );
+        assert_eq!(
+            pathing_grid.get_path_multiple_goals(Point::new(0, 0), vec![], &config),
+            Err(PathError::NoGoals)
+        );
+        pathing_grid.set_rectangle(&grid_util::Rect::new(3, 0, 1, 5), true);
+        assert_eq!(
+            pathing_grid.get_path_single_goal(Point::new(0, 0), goal, &config),
+            Err(PathError::GoalUnreachable)
+        );
+        // Changing the connectivity leaves the components stale until they are regenerated.
+        pathing_grid.set_connectivity(Connectivity::Four);
+        assert_eq!(
+            pathing_grid.get_path_single_goal(Point::new(0, 0), goal, &config),
+            Err(PathError::ComponentsDirty)
+        );
+        pathing_grid.update();
+        assert_eq!(
+            pathing_grid.get_path_single_goal(Point::new(0, 0), goal, &config),
+            Err(PathError::GoalUnreachable)
+        );
+    }
+
+    #[test]
+    fn test_four_connected_path() {
+        let mut pathing_grid = PathingGrid::new(5, 5, false);
+        pathing_grid.set_connectivity(Connectivity::Four);
+        pathing_grid.set(1, 1, true);
+        pathing_grid.set(2, 3, true);
+        pathing_grid.generate_components();
+        let start = Point::new(0, 0);
+        let goal = Point::new(4, 4);
+        let path = pathing_grid
+            .get_path_single_goal(start, goal, &SearchConfig::default())
+            .unwrap();
+        assert_eq!(path.len(), 9);
+        assert_eq!(path.first(), Some(&start));
+        assert_eq!(path.last(), Some(&goal));
+        assert!(path.windows(2).all(|w| w[0].manhattan_distance(&w[1]) == 1));
+        assert!(pathing_grid.can_step(&start, Direction::EAST));
+        assert!(!pathing_grid.can_step(&Point::new(2, 2), Direction::NORTHEAST));
+    }
+
+    /// The jump and successor generation of the original recursive implementation, ported
+    /// verbatim apart from taking the grid as an argument and from the later fix which charges the
+    /// successors of immediately expanded jump points for reaching them. They only support the
+    /// 8-connected, uniform-cost grids with corner cutting which that implementation was written
+    /// for.
+    mod baseline {
+        use super::*;
+
+        const IMPROVED_PRUNING: bool = true;
+
+        fn is_forced(grid: &PathingGrid, dir: Direction, node: &Point) -> bool {
+            let dir_num = dir.num();
+            if dir.diagonal() {
+                !grid.indexed_neighbor(node, 3 + dir_num) || !grid.indexed_neighbor(node, 5 + dir_num)
+            } else {
+                !grid.indexed_neighbor(node, 2 + dir_num) || !grid.indexed_neighbor(node, dir_num + 6)
+            }
+        }
+        fn pruned_neighborhood(grid: &PathingGrid, dir: Direction, node: &Point) -> (Vec<(Point, i32)>, bool) {
+            let dir_num = dir.num();
+            let mut n_mask: u8;
+            let neighbours = grid.neighbours.get_point(*node);
+            let mut forced = false;
+            if dir.diagonal() {
+                n_mask = 131_u8.rotate_left(dir_num as u32);
+                if !grid.indexed_neighbor(node, 3 + dir_num) {
+                    n_mask |= 1 << ((dir_num + 2) % 8);
+                    forced = true;
+                }
+                if !grid.indexed_neighbor(node, 5 + dir_num) {
+                    n_mask |= 1 << ((dir_num + 6) % 8);
+                    forced = true;
+                }
+            } else {
+                n_mask = 1 << dir_num;
+                if !grid.indexed_neighbor(node, 2 + dir_num) {
+                    n_mask |= 1 << ((dir_num + 1) % 8);
+                    forced = true;
+                }
+                if !grid.indexed_neighbor(node, 6 + dir_num) {
+                    n_mask |= 1 << ((dir_num + 7) % 8);
+                    forced = true;
+                }
+            }
+            let comb_mask = neighbours & n_mask;
+            (
+                (0..8)
+                    .filter(|x| comb_mask & (1 << *x) != 0)
+                    .map(|d| (node.moore_neighbor(d), 1))
+                    .collect::<Vec<(Point, i32)>>(),
+                forced,
+            )
+        }
+
+        pub fn jump<F>(
+            grid: &PathingGrid,
+            initial: &Point,
+            cost: i32,
+            direction: Direction,
+            goal: &F,
+        ) -> Option<(Point, i32)>
+            where
+                F: Fn(&Point) -> bool,
+        {
+            let new_n = *initial + direction;
+            if !grid.can_move_to(new_n) {
+                return None;
+            }
+
+            if goal(&new_n) {
+                return Some((new_n, cost));
+            }
+            if is_forced(grid, direction, &new_n) {
+                return Some((new_n, cost));
+            }
+            if direction.diagonal()
+                && (jump(grid, &new_n, 1, direction.x_dir(), goal).is_some()
+                || jump(grid, &new_n, 1, direction.y_dir(), goal).is_some())
+            {
+                return Some((new_n, cost));
+            }
+            jump(grid, &new_n, cost + 1, direction, goal)
+        }
+        fn pathfinding_neighborhood(grid: &PathingGrid, pos: &Point) -> Vec<(Point, i32)> {
+            pos.moore_neighborhood()
+                .into_iter()
+                .filter(|&position| grid.can_move_to(position))
+                .map(|p| (p, 1))
+                .collect::<Vec<_>>()
+        }
+        pub fn jps_neighbours<F>(grid: &PathingGrid, parent: Option<&Point>, node: &Point, goal: &F) -> Vec<(Point, i32)>
+            where
+                F: Fn(&Point) -> bool,
+        {
+            match parent {
+                Some(parent_node) => {
+                    let mut succ = vec![];
+                    let dir = parent_node.dir_obj(node);
+                    for (n, c) in &pruned_neighborhood(grid, dir, node).0 {
+                        let dir = node.dir_obj(n);
+                        if let Some((jumped_node, cost)) = jump(grid, node, *c, dir, goal) {
+                            let neighbour_dir = node.dir_obj(&jumped_node);
+                            if IMPROVED_PRUNING
+                                && dir.diagonal()
+                                && !is_forced(grid, neighbour_dir, &jumped_node)
+                            {
+                                let jump_points = jps_neighbours(grid, Some(node), &jumped_node, goal);
+                                succ.extend(jump_points.into_iter().map(|(p, c)| (p, cost + c)));
+                            }
+                            {
+                                succ.push((jumped_node, cost));
+                            }
+                        }
+                    }
+                    succ
+                }
+                None => pathfinding_neighborhood(grid, node),
+            }
+        }
+    }
+
+    /// Jumps and successors agree with the original recursive implementation.
+    #[test]
+    fn test_iterative_jumps() {
+        let blocked = |x: usize, y: usize| (x * 5 + y * 3) % 11 == 1 || (x + 2 * y) % 13 == 4;
+        let pathing_grid = PathingGrid::from_fn(40, 30, blocked);
+        let goals = [Point::new(39, 29), Point::new(17, 6)];
+        for (x, y) in (0..40).cartesian_product(0..30) {
+            let node = Point::new(x, y);
+            if !pathing_grid.can_move_to(node) {
+                continue;
+            }
+            for goal in &goals {
+                let reached = |p: &Point| p == goal;
+                assert_eq!(
+                    pathing_grid.jps_neighbours(None, &node, &reached),
+                    baseline::jps_neighbours(&pathing_grid, None, &node, &reached)
+                );
+                for i in 0..8 {
+                    let dir = Direction::try_from(i).unwrap();
+                    assert_eq!(
+                        pathing_grid.jump(&node, 1, dir, &reached, None, &mut 0),
+                        baseline::jump(&pathing_grid, &node, 1, dir, &reached)
+                    );
+                    let parent = node.moore_neighbor(i);
+                    let successors = pathing_grid.jps_neighbours(Some(&parent), &node, &reached);
+                    let expected =
+                        baseline::jps_neighbours(&pathing_grid, Some(&parent), &node, &reached);
+                    assert_eq!(successors, expected, "{} from {}", node, parent);
+                }
+            }
+        }
+    }
+
+    /// Long jumps do not need a deep stack.
+    #[test]
+    fn test_jumps_on_small_stack() {
+        std::thread::Builder::new()
+            .stack_size(64 * 1024)
+            .spawn(|| {
+                let mut pathing_grid = PathingGrid::new(2000, 2000, false);
+                pathing_grid.set_diagonal_policy(DiagonalPolicy::BothSidesFree);
+                let start = Point::new(0, 0);
+                let jump = pathing_grid.jump(&start, 1, Direction::NORTHEAST, &|_| false, None, &mut 0);
+                assert_eq!(jump, None);
+                let goal = Point::new(1999, 1500);
+                let reached = |p: &Point| *p == goal;
+                let jump = pathing_grid.jump(&start, 1, Direction::NORTHEAST, &reached, Some(&goal), &mut 0);
+                assert_eq!(jump, Some((Point::new(1500, 1500), 1500)));
+            })
+            .unwrap()
+            .join()
+            .unwrap();
     }
 }
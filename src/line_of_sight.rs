@@ -0,0 +1,324 @@
+//! Line of sight and raycasts on the obstacle grid of a [PathingGrid]. Lines run between the
+//! centres of cells and are walked as the sequence of steps between the cells they cross, where a
+//! line passing exactly through the corner of a cell takes a diagonal step. Whether such a corner
+//! can be passed follows the [DiagonalPolicy] as for moves on the grid, while the
+//! [supercover](supercover_cells) variants conservatively require every cell touched by the line
+//! to be free. Lines which can be walked are also used to smooth paths found on the grid.
+use grid_util::direction::Direction;
+use grid_util::grid::Grid;
+use grid_util::point::Point;
+
+use crate::{Connectivity, DiagonalPolicy, PathingGrid};
+
+/// The steps between the cells crossed by the line from the centre of a to the centre of b, as
+/// pairs of a cell and the direction towards the next cell. Following these steps moves a unit
+/// along the line, for example between the waypoints of an [any_angle](crate::any_angle) path.
+pub fn line_steps(a: Point, b: Point) -> impl Iterator<Item = (Point, Direction)> {
+    steps_along(a, (b.x as i64 - a.x as i64, b.y as i64 - a.y as i64))
+}
+
+/// The steps of [line_steps] from the centre of a along the given offset, which is kept in [i64]
+/// so that lines reaching beyond the range of a [Point] can be walked.
+fn steps_along(a: Point, (dx, dy): (i64, i64)) -> impl Iterator<Item = (Point, Direction)> {
+    let (sx, sy) = (dx.signum() as i32, dy.signum() as i32);
+    let (dx, dy) = (dx.abs(), dy.abs());
+    let (mut i, mut j) = (0, 0);
+    // The line crosses the i-th vertical cell border at (2i + 1) / 2dx of its length and the j-th
+    // horizontal cell border at (2j + 1) / 2dy, whichever comes first is crossed next. Their
+    // difference (2i + 1)dy - (2j + 1)dx is tracked instead of multiplying, which can overflow.
+    let mut order = dy - dx;
+    let mut cell = a;
+    std::iter::from_fn(move || {
+        if i == dx && j == dy {
+            return None;
+        }
+        let crossing = if i == dx {
+            Point::new(0, sy)
+        } else if j == dy {
+            Point::new(sx, 0)
+        } else {
+            match order.cmp(&0) {
+                std::cmp::Ordering::Less => Point::new(sx, 0),
+                std::cmp::Ordering::Greater => Point::new(0, sy),
+                std::cmp::Ordering::Equal => Point::new(sx, sy),
+            }
+        };
+        if crossing.x != 0 {
+            i += 1;
+            order += 2 * dy;
+        }
+        if crossing.y != 0 {
+            j += 1;
+            order -= 2 * dx;
+        }
+        let step = (cell, cell.dir_obj(&(cell + crossing)));
+        cell = cell + crossing;
+        Some(step)
+    })
+}
+
+/// The cells touched by the line from the centre of a to the centre of b, starting at a. Unlike
+/// [line_steps], both cells beside a corner the line passes through are included.
+pub fn supercover_cells(a: Point, b: Point) -> impl Iterator<Item = Point> {
+    std::iter::once(a).chain(line_steps(a, b).flat_map(|(cell, dir)| {
+        let sides = dir
+            .diagonal()
+            .then(|| [cell + dir.x_dir(), cell + dir.y_dir()]);
+        sides.into_iter().flatten().chain([cell + dir])
+    }))
+}
+
+impl PathingGrid {
+    /// Checks whether a single step along a line is possible. Like moves on the grid, stepping
+    /// through a corner is subject to the [DiagonalPolicy], and a 4-connected grid requires one of
+    /// the sides to be free.
+    pub(crate) fn can_cross(&self, from: &Point, dir: Direction) -> bool {
+        if !self.can_move_to(*from + dir) {
+            return false;
+        }
+        if !dir.diagonal() {
+            return true;
+        }
+        let sides = [dir.x_dir(), dir.y_dir()].map(|side| self.can_move_to(*from + side));
+        match (self.connectivity, self.diagonal_policy) {
+            (Connectivity::Eight, DiagonalPolicy::Always) => true,
+            (Connectivity::Eight, DiagonalPolicy::BothSidesFree) => sides[0] && sides[1],
+            _ => sides[0] || sides[1],
+        }
+    }
+    /// Checks whether a unit can move along the straight line between the centres of a and b
+    /// without crossing a blocked cell. Both a and b need to be free cells on the grid.
+    pub fn line_of_sight(&self, a: &Point, b: &Point) -> bool {
+        self.can_move_to(*a) && line_steps(*a, *b).all(|(cell, dir)| self.can_cross(&cell, dir))
+    }
+    /// Like [line_of_sight](Self::line_of_sight), but also requires both cells beside every corner
+    /// the line passes through to be free, regardless of the [DiagonalPolicy].
+    pub fn supercover_line_of_sight(&self, a: &Point, b: &Point) -> bool {
+        supercover_cells(*a, *b).all(|p| self.can_move_to(p))
+    }
+    /// Casts a ray from the centre of origin along the given direction, and returns the first
+    /// blocked cell it hits within max_distance of origin, measured between cell centres. A corner
+    /// which can not be passed is hit at one of the blocked cells beside it. Returns [None] if the
+    /// ray leaves the grid or the distance first, or if the direction is zero.
+    pub fn raycast(&self, origin: Point, direction: Point, max_distance: i32) -> Option<Point> {
+        if !self.point_in_bounds(origin) {
+            return None;
+        }
+        if self.grid.get_point(origin) {
+            return Some(origin);
+        }
+        let (x, y) = (direction.x as i64, direction.y as i64);
+        let reach = x.abs().max(y.abs());
+        if reach == 0 {
+            return None;
+        }
+        let max_distance = max_distance.max(0) as i64;
+        // A line this long runs past max_distance in every direction, or leaves the grid.
+        let bound = (self.width() + self.height()) as i64;
+        let scale = (max_distance / reach).min(bound) + 1;
+        let in_range = |p: &Point| {
+            let (dx, dy) = ((p.x - origin.x) as i64, (p.y - origin.y) as i64);
+            dx * dx + dy * dy <= max_distance * max_distance
+        };
+        for (cell, dir) in steps_along(origin, (x * scale, y * scale)) {
+            let next = cell + dir;
+            if !in_range(&next) || !self.point_in_bounds(next) {
+                return None;
+            }
+            if !self.can_cross(&cell, dir) {
+                let candidates = [next, cell + dir.x_dir(), cell + dir.y_dir()];
+                return candidates.into_iter().find(|p| self.grid.get_point(*p));
+            }
+        }
+        None
+    }
+    /// Removes redundant waypoints by pulling the path taut: each waypoint is joined to the
+    /// furthest point along the path it has a line of sight to. Takes the waypoints of a search or
+    /// a path from [waypoints_to_path](Self::waypoints_to_path), and returns waypoints joined by
+    /// straight lines which can be followed with [line_steps].
+    pub fn smooth_waypoints(&self, waypoints: Vec<Point>) -> Vec<Point> {
+        if waypoints.is_empty() {
+            return waypoints;
+        }
+        let path = self.waypoints_to_path(waypoints);
+        let (&first, rest) = path.split_first().unwrap();
+        let mut smoothed = vec![first];
+        // Neighbouring points along the path always see each other, so every step makes progress.
+        for (previous, point) in path.iter().zip(rest) {
+            if !self.line_of_sight(smoothed.last().unwrap(), point) {
+                smoothed.push(*previous);
+            }
+        }
+        smoothed.extend(path.last().filter(|last| *last != smoothed.last().unwrap()));
+        smoothed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use super::*;
+    use crate::any_angle::euclidean_cost;
+    use crate::SearchConfig;
+
+    #[test]
+    fn walks_crossed_cells() {
+        let cells =
+            |a: Point, b: Point| line_steps(a, b).map(|(cell, dir)| cell + dir).collect_vec();
+        let points =
+            |cells: &[(i32, i32)]| cells.iter().map(|&(x, y)| Point::new(x, y)).collect_vec();
+        let (a, b) = (Point::new(0, 0), Point::new(4, 1));
+        assert_eq!(
+            cells(a, b),
+            points(&[(1, 0), (2, 0), (2, 1), (3, 1), (4, 1)])
+        );
+        assert_eq!(
+            cells(b, a),
+            points(&[(3, 1), (2, 1), (2, 0), (1, 0), (0, 0)])
+        );
+        // Lines through corners step diagonally.
+        assert_eq!(
+            cells(a, Point::new(3, 1)),
+            points(&[(1, 0), (2, 1), (3, 1)])
+        );
+        assert_eq!(cells(a, Point::new(-2, 2)), points(&[(-1, 1), (-2, 2)]));
+        assert_eq!(cells(a, a), []);
+        let far = Point::new(i32::MAX, i32::MAX);
+        let steps = line_steps(Point::new(-1, -1), far).take(3);
+        assert_eq!(
+            steps.map(|(cell, dir)| cell + dir).collect_vec(),
+            points(&[(0, 0), (1, 1), (2, 2)])
+        );
+    }
+
+    #[test]
+    fn respects_diagonal_policy() {
+        let mut pathing_grid: PathingGrid = "
+            ....
+            .#..
+            ....
+        "
+        .parse()
+        .unwrap();
+        let blocked = (0..3)
+            .flat_map(|y| (0..4).map(move |x| Point::new(x, y)))
+            .find(|p| !pathing_grid.can_move_to(*p))
+            .unwrap();
+        let corner = (blocked + Direction::WEST, blocked + Direction::NORTH);
+        assert!(
+            !pathing_grid.line_of_sight(&(blocked + Direction::WEST), &(blocked + Direction::EAST))
+        );
+        for (policy, clear) in [
+            (DiagonalPolicy::Always, true),
+            (DiagonalPolicy::OneSideFree, true),
+            (DiagonalPolicy::BothSidesFree, false),
+        ] {
+            pathing_grid.set_diagonal_policy(policy);
+            assert_eq!(pathing_grid.line_of_sight(&corner.0, &corner.1), clear);
+            assert_eq!(pathing_grid.line_of_sight(&corner.1, &corner.0), clear);
+        }
+        assert!(!pathing_grid.line_of_sight(&Point::new(0, 0), &Point::new(-1, 0)));
+    }
+
+    /// Smoothed waypoints see each other and shorten the path.
+    #[test]
+    fn smooths_waypoints() {
+        let map = crate::ascii::parse(
+            "
+            S.........#.........
+            ..........#.........
+            ..####....#....###..
+            ..#.......#......#..
+            ..#...#####......#..
+            ..#..............#..
+            ..######...#######..
+            .........#..........
+            .........#.........G
+        ",
+        )
+        .unwrap();
+        let (start, goal) = (map.start.unwrap(), map.goals[0]);
+        let length = |points: &[Point]| {
+            points
+                .iter()
+                .tuple_windows()
+                .map(|(a, b)| euclidean_cost(a, b))
+                .sum::<i32>()
+        };
+        for policy in [
+            DiagonalPolicy::Always,
+            DiagonalPolicy::OneSideFree,
+            DiagonalPolicy::BothSidesFree,
+        ] {
+            let mut pathing_grid = map.grid.clone();
+            pathing_grid.set_diagonal_policy(policy);
+            pathing_grid.update();
+            let waypoints = pathing_grid
+                .get_waypoints_single_goal(start, goal, &SearchConfig::optimal())
+                .unwrap();
+            let path = pathing_grid.waypoints_to_path(waypoints.clone());
+            let smoothed = pathing_grid.smooth_waypoints(waypoints);
+            assert_eq!(smoothed, pathing_grid.smooth_waypoints(path.clone()));
+            assert_eq!((smoothed[0], *smoothed.last().unwrap()), (start, goal));
+            for (a, b) in smoothed.iter().tuple_windows() {
+                assert!(pathing_grid.line_of_sight(a, b), "{} -> {}", a, b);
+            }
+            assert!(length(&smoothed) < length(&path), "{:?}", policy);
+        }
+        let pathing_grid = map.grid;
+        assert_eq!(pathing_grid.smooth_waypoints(vec![start]), [start]);
+        assert_eq!(pathing_grid.smooth_waypoints(Vec::new()), []);
+    }
+
+    #[test]
+    fn casts_rays() {
+        let pathing_grid = PathingGrid::from_fn(8, 5, |x, y| (x, y) == (5, 2) || (x, y) == (2, 4));
+        let origin = Point::new(1, 2);
+        let east = Point::new(1, 0);
+        assert_eq!(
+            pathing_grid.raycast(origin, east, 10),
+            Some(Point::new(5, 2))
+        );
+        assert_eq!(pathing_grid.raycast(origin, east, 3), None);
+        assert_eq!(pathing_grid.raycast(origin, east * -1, 10), None);
+        assert_eq!(
+            pathing_grid.raycast(origin, Point::new(1, 2), 10),
+            Some(Point::new(2, 4))
+        );
+        assert_eq!(pathing_grid.raycast(origin, Point::new(0, 0), 10), None);
+        assert_eq!(
+            pathing_grid.raycast(Point::new(5, 2), east, 10),
+            Some(Point::new(5, 2))
+        );
+        assert_eq!(pathing_grid.raycast(Point::new(-1, 2), east, 10), None);
+        assert_eq!(
+            pathing_grid.raycast(origin, east, i32::MAX),
+            Some(Point::new(5, 2))
+        );
+        assert_eq!(
+            pathing_grid.raycast(origin, Point::new(i32::MAX, i32::MIN), i32::MAX),
+            None
+        );
+        assert_eq!(
+            pathing_grid.raycast(origin, Point::new(i32::MAX / 2, i32::MAX), i32::MAX),
+            Some(Point::new(2, 4))
+        );
+        assert_eq!(pathing_grid.raycast(origin, east, -10), None);
+    }
+
+    /// Supercover lines are blocked by corners which ordinary lines of sight may pass.
+    #[test]
+    fn supercover_is_conservative() {
+        let pathing_grid = PathingGrid::from_fn(4, 4, |x, y| (x, y) == (1, 0));
+        let (a, b) = (Point::new(0, 0), Point::new(2, 2));
+        assert_eq!(
+            supercover_cells(a, Point::new(1, 1)).collect_vec(),
+            [a, Point::new(1, 0), Point::new(0, 1), Point::new(1, 1)]
+        );
+        assert!(pathing_grid.line_of_sight(&a, &b));
+        assert!(!pathing_grid.supercover_line_of_sight(&a, &b));
+        assert!(pathing_grid.supercover_line_of_sight(&Point::new(0, 1), &Point::new(3, 3)));
+        assert!(!pathing_grid.supercover_line_of_sight(&Point::new(0, 1), &Point::new(4, 1)));
+    }
+}
@@ -0,0 +1,329 @@
+//! Loaders for the [Moving AI grid benchmarks](https://movingai.com/benchmarks/grids.html). Maps
+//! in the `.map` format become a [PathingGrid] and `.scen` files become a list of [Scenario]s,
+//! holding the start, goal and optimal length of each problem.
+//!
+//! The benchmarks use octile movement in which diagonal moves may not cut corners, so the grids
+//! are set up with [DiagonalPolicy::BothSidesFree] and [CostModel::Octile]. Passable terrain
+//! (`.`, `G` and `S`) becomes free, while out of bounds terrain (`@`, `O`), trees (`T`) and water
+//! (`W`) become blocked.
+use core::fmt;
+use std::fs;
+use std::path::Path;
+
+use grid_util::grid::{BoolGrid, Grid};
+use grid_util::point::Point;
+
+use crate::{Connectivity, CostModel, DiagonalPolicy, PathingGrid};
+
+/// An error encountered while loading a benchmark file.
+#[derive(Debug)]
+pub enum MovingAiError {
+    /// The file could not be read.
+    Io(std::io::Error),
+    /// The contents are malformed at the given line, counting from 1.
+    Parse { line: usize, reason: String },
+}
+
+impl fmt::Display for MovingAiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MovingAiError::Io(err) => write!(f, "could not read file: {}", err),
+            MovingAiError::Parse { line, reason } => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl std::error::Error for MovingAiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MovingAiError::Io(err) => Some(err),
+            MovingAiError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for MovingAiError {
+    fn from(err: std::io::Error) -> Self {
+        MovingAiError::Io(err)
+    }
+}
+
+fn parse_error(line: usize, reason: impl Into<String>) -> MovingAiError {
+    MovingAiError::Parse {
+        line,
+        reason: reason.into(),
+    }
+}
+
+/// A single problem from a `.scen` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Scenario {
+    /// The bucket the problem belongs to, grouping problems of similar length.
+    pub bucket: usize,
+    /// The file name of the map.
+    pub map: String,
+    pub width: usize,
+    pub height: usize,
+    pub start: Point,
+    pub goal: Point,
+    /// The length of an optimal path, with diagonal moves of length √2. Searches with
+    /// [CostModel::Octile] minimise costs in which √2 is truncated to 1.414, so the
+    /// [octile_length] of the paths they find can exceed this by up to about 2.1e-4 per diagonal
+    /// move, which is bounded by 2.1e-4 times the length.
+    pub optimal_length: f64,
+}
+
+/// Whether a terrain character is passable.
+fn passable(c: char) -> Option<bool> {
+    match c {
+        '.' | 'G' | 'S' => Some(true),
+        '@' | 'O' | 'T' | 'W' => Some(false),
+        _ => None,
+    }
+}
+
+/// Parses the contents of a `.map` file into a [PathingGrid] with generated components. Rows are
+/// mapped to increasing y coordinates, matching the coordinates of [Scenario]s.
+pub fn parse_map(contents: &str) -> Result<PathingGrid, MovingAiError> {
+    let mut lines = contents
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim_end()));
+    let mut width = None;
+    let mut height = None;
+    // The header ends at the line starting the map section.
+    let map_line = loop {
+        let (line, text) = lines
+            .next()
+            .ok_or_else(|| parse_error(contents.lines().count(), "missing map section"))?;
+        let mut words = text.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("type"), Some("octile")) => {}
+            (Some("type"), Some(other)) => {
+                return Err(parse_error(line, format!("unsupported map type {}", other)))
+            }
+            (Some("height"), Some(value)) => {
+                height = Some(
+                    value
+                        .parse()
+                        .map_err(|_| parse_error(line, "invalid height"))?,
+                )
+            }
+            (Some("width"), Some(value)) => {
+                width = Some(
+                    value
+                        .parse()
+                        .map_err(|_| parse_error(line, "invalid width"))?,
+                )
+            }
+            (Some("map"), None) => break line,
+            _ => {
+                return Err(parse_error(
+                    line,
+                    format!("unexpected header line {:?}", text),
+                ))
+            }
+        }
+    };
+    let (width, height): (usize, usize) = match (width, height) {
+        (Some(width), Some(height)) => (width, height),
+        _ => return Err(parse_error(map_line, "missing width or height")),
+    };
+    let mut grid = BoolGrid::new(width, height, false);
+    for y in 0..height {
+        let (line, row) = lines.next().ok_or_else(|| {
+            parse_error(
+                contents.lines().count(),
+                format!("expected {} rows, got {}", height, y),
+            )
+        })?;
+        if row.chars().count() != width {
+            return Err(parse_error(line, format!("expected {} cells", width)));
+        }
+        for (x, c) in row.chars().enumerate() {
+            let free =
+                passable(c).ok_or_else(|| parse_error(line, format!("unknown terrain {:?}", c)))?;
+            grid.set(x, y, !free);
+        }
+    }
+    Ok(PathingGrid::from_parts(
+        grid,
+        Connectivity::Eight,
+        DiagonalPolicy::BothSidesFree,
+        CostModel::Octile,
+    ))
+}
+
+/// Reads and parses a `.map` file, see [parse_map].
+pub fn load_map<P: AsRef<Path>>(path: P) -> Result<PathingGrid, MovingAiError> {
+    parse_map(&fs::read_to_string(path)?)
+}
+
+/// Parses the contents of a `.scen` file.
+pub fn parse_scenarios(contents: &str) -> Result<Vec<Scenario>, MovingAiError> {
+    let mut lines = contents
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim_end()));
+    match lines.next() {
+        Some((_, header)) if header.starts_with("version") => {}
+        _ => return Err(parse_error(1, "missing version header")),
+    }
+    lines
+        .filter(|(_, text)| !text.is_empty())
+        .map(|(line, text)| {
+            let fields = text.split('\t').collect::<Vec<_>>();
+            if fields.len() != 9 {
+                return Err(parse_error(
+                    line,
+                    format!("expected 9 fields, got {}", fields.len()),
+                ));
+            }
+            let int = |i: usize| {
+                fields[i]
+                    .parse::<usize>()
+                    .map_err(|_| parse_error(line, format!("invalid field {:?}", fields[i])))
+            };
+            Ok(Scenario {
+                bucket: int(0)?,
+                map: fields[1].to_string(),
+                width: int(2)?,
+                height: int(3)?,
+                start: Point::new(int(4)? as i32, int(5)? as i32),
+                goal: Point::new(int(6)? as i32, int(7)? as i32),
+                optimal_length: fields[8]
+                    .parse()
+                    .map_err(|_| parse_error(line, "invalid optimal length"))?,
+            })
+        })
+        .collect()
+}
+
+/// Reads and parses a `.scen` file, see [parse_scenarios].
+pub fn load_scenarios<P: AsRef<Path>>(path: P) -> Result<Vec<Scenario>, MovingAiError> {
+    parse_scenarios(&fs::read_to_string(path)?)
+}
+
+/// The length of a path or of its waypoints with diagonal moves of length √2, comparable to
+/// [Scenario::optimal_length]. Path costs under [CostModel::Octile] round √2 down, so these
+/// lengths are more precise. Comparisons with the optimal length should allow for the tolerance
+/// described there, which grows with the length of the path.
+pub fn octile_length(path: &[Point]) -> f64 {
+    path.iter()
+        .zip(path.iter().skip(1))
+        .map(|(a, b)| {
+            let dx = (a.x - b.x).abs();
+            let dy = (a.y - b.y).abs();
+            (dx.max(dy) - dx.min(dy)) as f64 + dx.min(dy) as f64 * std::f64::consts::SQRT_2
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SearchConfig;
+
+    const MAP: &str = "type octile\nheight 4\nwidth 5\nmap\n.....\n.@@T.\n.....\nWWS..\n";
+    const SCEN: &str = "version 1\n\
+        0\tsmall.map\t5\t4\t0\t0\t4\t2\t6.00000000\n\
+        0\tsmall.map\t5\t4\t2\t3\t4\t0\t4.41421356\n";
+
+    #[test]
+    fn parses_map() {
+        let pathing_grid = parse_map(MAP).unwrap();
+        assert_eq!((pathing_grid.width(), pathing_grid.height()), (5, 4));
+        assert!(pathing_grid.get(1, 1) && pathing_grid.get(3, 1) && pathing_grid.get(0, 3));
+        assert!(!pathing_grid.get(2, 3));
+        assert!(!pathing_grid.components_dirty());
+        assert!(parse_map("type octile\nheight 1\nwidth 2\nmap\n.X\n").is_err());
+        assert!(matches!(
+            parse_map("type octile\nheight 1\nmap\n..\n"),
+            Err(MovingAiError::Parse { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn matches_optimal_lengths() {
+        let pathing_grid = parse_map(MAP).unwrap();
+        let scenarios = parse_scenarios(SCEN).unwrap();
+        assert_eq!(scenarios.len(), 2);
+        for scenario in scenarios {
+            let path = pathing_grid
+                .get_path_single_goal(scenario.start, scenario.goal, &SearchConfig::optimal())
+                .unwrap();
+            assert!((octile_length(&path) - scenario.optimal_length).abs() < 1e-6);
+        }
+    }
+
+    /// The optimal lengths from start to every cell, with diagonal moves of length √2.
+    fn reference_lengths(pathing_grid: &PathingGrid, start: Point) -> Vec<f64> {
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
+        let width = pathing_grid.width();
+        let index = |p: Point| p.x as usize + p.y as usize * width;
+        let mut lengths = vec![f64::INFINITY; width * pathing_grid.height()];
+        let mut queue = BinaryHeap::new();
+        lengths[index(start)] = 0.0;
+        // Non-negative floats are ordered like their bits.
+        queue.push(Reverse((0f64.to_bits(), start)));
+        while let Some(Reverse((bits, point))) = queue.pop() {
+            if f64::from_bits(bits) > lengths[index(point)] {
+                continue;
+            }
+            for next in point.moore_neighborhood() {
+                let corner = [Point::new(next.x, point.y), Point::new(point.x, next.y)];
+                if !pathing_grid.can_move_to(next)
+                    || !corner.iter().all(|p| pathing_grid.can_move_to(*p))
+                {
+                    continue;
+                }
+                let step = octile_length(&[point, next]);
+                let length = f64::from_bits(bits) + step;
+                if length < lengths[index(next)] {
+                    lengths[index(next)] = length;
+                    queue.push(Reverse((length.to_bits(), next)));
+                }
+            }
+        }
+        lengths
+    }
+
+    /// Path lengths on a larger map stay within a tolerance proportional to the optimal length.
+    #[test]
+    fn matches_optimal_lengths_on_larger_map() {
+        let (width, height) = (64, 48);
+        let rows = (0..height)
+            .map(|y| {
+                (0..width)
+                    .map(|x| if (x * 7 + y * 13) % 11 == 3 { 'T' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>();
+        let map = format!(
+            "type octile\nheight {}\nwidth {}\nmap\n{}\n",
+            height,
+            width,
+            rows.join("\n")
+        );
+        let pathing_grid = parse_map(&map).unwrap();
+        let start = Point::new(0, 0);
+        let lengths = reference_lengths(&pathing_grid, start);
+        let mut scen = String::from("version 1\n");
+        for goal in [(63, 47), (63, 0), (1, 46), (40, 30)] {
+            let length = lengths[goal.0 + goal.1 * width];
+            scen += &format!(
+                "0\tlarge.map\t{}\t{}\t0\t0\t{}\t{}\t{:.8}\n",
+                width, height, goal.0, goal.1, length
+            );
+        }
+        for scenario in parse_scenarios(&scen).unwrap() {
+            let path = pathing_grid
+                .get_path_single_goal(scenario.start, scenario.goal, &SearchConfig::optimal())
+                .unwrap();
+            let tolerance = (std::f64::consts::SQRT_2 - 1.414) * scenario.optimal_length + 1e-6;
+            let error = octile_length(&path) - scenario.optimal_length;
+            assert!(error.abs() <= tolerance, "{} {}", scenario.goal, error);
+        }
+    }
+}
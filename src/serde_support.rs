@@ -0,0 +1,100 @@
+//! Serialization of [PathingGrid], enabled by the `serde` feature. Only the bit-packed occupancy
+//! and the movement settings are stored. The neighbours and components are derived from these, so
+//! they are rebuilt on deserialization and the loaded grid is immediately ready for queries.
+use grid_util::grid::BoolGrid;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{Connectivity, CostModel, DiagonalPolicy, PathingGrid};
+
+#[derive(Serialize)]
+struct GridRef<'a> {
+    grid: &'a BoolGrid,
+    connectivity: Connectivity,
+    diagonal_policy: DiagonalPolicy,
+    cost_model: CostModel,
+}
+
+#[derive(Deserialize)]
+struct GridData {
+    grid: BoolGrid,
+    connectivity: Connectivity,
+    diagonal_policy: DiagonalPolicy,
+    cost_model: CostModel,
+}
+
+impl Serialize for PathingGrid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GridRef {
+            grid: &self.grid,
+            connectivity: self.connectivity,
+            diagonal_policy: self.diagonal_policy,
+            cost_model: self.cost_model,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PathingGrid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = GridData::deserialize(deserializer)?;
+        let expected = data
+            .grid
+            .width
+            .checked_mul(data.grid.height)
+            .map(|cells| 1 + cells / 64);
+        if expected != Some(data.grid.values.len()) {
+            return Err(D::Error::custom(format!(
+                "{} words do not fit a {}x{} grid",
+                data.grid.values.len(),
+                data.grid.width,
+                data.grid.height
+            )));
+        }
+        Ok(PathingGrid::from_parts(
+            data.grid,
+            data.connectivity,
+            data.diagonal_policy,
+            data.cost_model,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grid_util::grid::Grid;
+    use grid_util::point::Point;
+
+    use crate::{DiagonalPolicy, PathingGrid, SearchConfig};
+
+    #[test]
+    fn round_trip() {
+        let mut pathing_grid: PathingGrid = "
+            S..#....
+            ##.#.##.
+            ...#..#.
+            .#....#G
+        "
+        .parse()
+        .unwrap();
+        pathing_grid.set_diagonal_policy(DiagonalPolicy::OneSideFree);
+        pathing_grid.update();
+        let serialized = ron::to_string(&pathing_grid).unwrap();
+        let loaded: PathingGrid = ron::from_str(&serialized).unwrap();
+        assert_eq!(loaded.to_string(), pathing_grid.to_string());
+        assert_eq!(loaded.neighbours().values, pathing_grid.neighbours().values);
+        assert_eq!(loaded.diagonal_policy(), DiagonalPolicy::OneSideFree);
+        assert!(!loaded.components_dirty());
+        let (start, goal) = (Point::new(0, 0), Point::new(7, 3));
+        assert_eq!(
+            loaded.get_path_single_goal(start, goal, &SearchConfig::optimal()),
+            pathing_grid.get_path_single_goal(start, goal, &SearchConfig::optimal())
+        );
+        assert!(ron::from_str::<PathingGrid>(
+            "(grid: (width: 100, height: 100, values: [0]), connectivity: Eight, \
+             diagonal_policy: Always, cost_model: Uniform)"
+        )
+        .is_err());
+        assert_eq!(loaded.width(), 8);
+    }
+}
@@ -0,0 +1,349 @@
+//! A versioned binary snapshot of a [PathingGrid] for fast level loading. Unlike building a grid
+//! cell by cell, loading a snapshot copies the precomputed neighbour masks and component labels
+//! in bulk. All values are little-endian and laid out as follows:
+//!
+//! | Bytes | Contents |
+//! |-------|----------|
+//! | 4 | The magic bytes `GPSN` |
+//! | 2 | The format version, currently [SNAPSHOT_VERSION] |
+//! | 4 | The [Connectivity], [DiagonalPolicy] and [CostModel] as `u8`, then whether the components are dirty |
+//! | 2 | Reserved, zero |
+//! | 4 × 3 | The width, height and number of component labels as `u32` |
+//! | 8 × words | The bit-packed obstacles as `u64`, with `1 + width * height / 64` words |
+//! | 1 × cells | The neighbour mask of every cell |
+//! | 4 × cells | The component label of every cell as `u32`, with `u32::MAX` for blocked cells |
+//! | 4 × labels | The size of every component label as `u32` |
+//!
+//! The component labels are trusted to be consistent, apart from checks that keep the loaded grid
+//! from indexing out of bounds and that the component sizes match the labels. The neighbour masks
+//! have to match the obstacles, as searches rely on them instead of the obstacles. The
+//! transposed copy of the obstacles used by [bitscan](crate::bitscan) is derived from them while
+//! loading rather than stored, so it can never disagree with them.
+use core::fmt;
+use std::io::{self, Read, Write};
+use std::num::TryFromIntError;
+
+use grid_util::grid::{BoolGrid, SimpleGrid};
+use grid_util::point::Point;
+
+use crate::components::{Components, NO_COMPONENT};
+use crate::{Connectivity, CostModel, DiagonalPolicy, PathingGrid};
+
+const MAGIC: &[u8; 4] = b"GPSN";
+/// The version of the snapshot format written by [PathingGrid::write_snapshot].
+pub const SNAPSHOT_VERSION: u16 = 1;
+const HEADER_LEN: usize = 24;
+
+/// An error encountered while loading a snapshot.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The snapshot could not be read.
+    Io(io::Error),
+    /// The data does not start with the magic bytes of a snapshot.
+    BadMagic,
+    /// The snapshot was written in a format version which is not supported.
+    UnsupportedVersion(u16),
+    /// The data ends before the snapshot does.
+    Truncated,
+    /// The snapshot holds a value which is out of range.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "could not read snapshot: {}", err),
+            SnapshotError::BadMagic => write!(f, "not a grid snapshot"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {}", version)
+            }
+            SnapshotError::Truncated => write!(f, "snapshot is truncated"),
+            SnapshotError::Invalid(reason) => write!(f, "invalid snapshot: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SnapshotError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+
+/// Reads consecutive values from the snapshot data.
+struct Cursor<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.data.len() < len {
+            return Err(SnapshotError::Truncated);
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+    fn array<const N: usize>(&mut self, count: usize) -> Result<Vec<[u8; N]>, SnapshotError> {
+        let len = count.checked_mul(N).ok_or(SnapshotError::Truncated)?;
+        Ok(self
+            .take(len)?
+            .chunks_exact(N)
+            .map(|chunk| chunk.try_into().unwrap())
+            .collect())
+    }
+    fn u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.take(1)?[0])
+    }
+    fn u16(&mut self) -> Result<u16, SnapshotError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+fn label_to_u32(label: usize) -> Result<u32, TryFromIntError> {
+    if label == NO_COMPONENT {
+        Ok(u32::MAX)
+    } else {
+        u32::try_from(label)
+    }
+}
+
+impl PathingGrid {
+    /// Writes a snapshot of the grid, see [snapshot](crate::snapshot) for the format. Fails with
+    /// [io::ErrorKind::InvalidInput] if the width, height or number of component labels does not
+    /// fit in a `u32`.
+    pub fn write_snapshot<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let labels = self.components.labels();
+        let sizes = self.components.sizes();
+        let too_large = |_| io::Error::new(io::ErrorKind::InvalidInput, "grid too large");
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(&SNAPSHOT_VERSION.to_le_bytes());
+        header.push(match self.connectivity {
+            Connectivity::Eight => 0,
+            Connectivity::Four => 1,
+        });
+        header.push(match self.diagonal_policy {
+            DiagonalPolicy::Always => 0,
+            DiagonalPolicy::OneSideFree => 1,
+            DiagonalPolicy::BothSidesFree => 2,
+        });
+        header.push(match self.cost_model {
+            CostModel::Uniform => 0,
+            CostModel::Octile => 1,
+        });
+        header.push(self.components_dirty as u8);
+        header.extend_from_slice(&[0, 0]);
+        for value in [self.grid.width, self.grid.height, sizes.len()] {
+            header.extend_from_slice(&u32::try_from(value).map_err(too_large)?.to_le_bytes());
+        }
+        writer.write_all(&header)?;
+        let words = self
+            .grid
+            .values
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect::<Vec<_>>();
+        writer.write_all(&words)?;
+        writer.write_all(&self.neighbours.values)?;
+        let mut bytes = Vec::with_capacity(4 * labels.len());
+        for &label in labels {
+            bytes.extend_from_slice(&label_to_u32(label).map_err(too_large)?.to_le_bytes());
+        }
+        writer.write_all(&bytes)?;
+        let mut bytes = Vec::with_capacity(4 * sizes.len());
+        for &size in sizes {
+            bytes.extend_from_slice(&u32::try_from(size).map_err(too_large)?.to_le_bytes());
+        }
+        writer.write_all(&bytes)
+    }
+    /// Produces a snapshot of the grid in memory.
+    ///
+    /// # Panics
+    /// Panics if the width, height or number of component labels does not fit in a `u32`, see
+    /// [write_snapshot](Self::write_snapshot).
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.write_snapshot(&mut bytes)
+            .expect("grid is too large for a snapshot");
+        bytes
+    }
+    /// Loads a grid from a snapshot, ready for queries.
+    pub fn from_snapshot(data: &[u8]) -> Result<PathingGrid, SnapshotError> {
+        let mut cursor = Cursor { data };
+        if cursor.take(4)? != MAGIC {
+            return Err(SnapshotError::BadMagic);
+        }
+        let version = cursor.u16()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let connectivity = match cursor.u8()? {
+            0 => Connectivity::Eight,
+            1 => Connectivity::Four,
+            _ => return Err(SnapshotError::Invalid("unknown connectivity")),
+        };
+        let diagonal_policy = match cursor.u8()? {
+            0 => DiagonalPolicy::Always,
+            1 => DiagonalPolicy::OneSideFree,
+            2 => DiagonalPolicy::BothSidesFree,
+            _ => return Err(SnapshotError::Invalid("unknown diagonal policy")),
+        };
+        let cost_model = match cursor.u8()? {
+            0 => CostModel::Uniform,
+            1 => CostModel::Octile,
+            _ => return Err(SnapshotError::Invalid("unknown cost model")),
+        };
+        let components_dirty = cursor.u8()? != 0;
+        cursor.take(2)?;
+        let width = cursor.u32()? as usize;
+        let height = cursor.u32()? as usize;
+        let label_count = cursor.u32()? as usize;
+        let cells = width
+            .checked_mul(height)
+            .ok_or(SnapshotError::Invalid("grid too large"))?;
+        let values = cursor
+            .array::<8>(1 + cells / 64)?
+            .into_iter()
+            .map(u64::from_le_bytes)
+            .collect();
+        let masks = cursor.take(cells)?.to_vec();
+        let labels = cursor
+            .array::<4>(cells)?
+            .into_iter()
+            .map(|bytes| match u32::from_le_bytes(bytes) {
+                u32::MAX => Ok(NO_COMPONENT),
+                label if (label as usize) < label_count => Ok(label as usize),
+                _ => Err(SnapshotError::Invalid("component label out of range")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let sizes = cursor
+            .array::<4>(label_count)?
+            .into_iter()
+            .map(|bytes| u32::from_le_bytes(bytes) as usize)
+            .collect::<Vec<_>>();
+        if !cursor.data.is_empty() {
+            return Err(SnapshotError::Invalid("trailing data"));
+        }
+        // Components are updated in place by their sizes, which have to count the labels exactly.
+        let mut counted = vec![0; label_count];
+        for &label in labels.iter().filter(|&&label| label != NO_COMPONENT) {
+            counted[label] += 1;
+        }
+        if counted != sizes {
+            return Err(SnapshotError::Invalid(
+                "component sizes do not match labels",
+            ));
+        }
+        let grid = BoolGrid {
+            width,
+            height,
+            values,
+        };
+        let pathing_grid = PathingGrid {
+            transposed: crate::bitscan::transpose(&grid),
+            grid,
+            neighbours: SimpleGrid {
+                width,
+                height,
+                values: masks,
+            },
+            components: Components::from_raw(labels, sizes),
+            components_dirty,
+            connectivity,
+            diagonal_policy,
+            cost_model,
+            jump_table: None,
+        };
+        // Every search reads the masks rather than the obstacles, so they have to agree.
+        let consistent = (0..cells).all(|ix| {
+            let point = Point::new((ix % width) as i32, (ix / width) as i32);
+            pathing_grid.neighbours.values[ix] == pathing_grid.neighbour_mask(&point)
+        });
+        if !consistent {
+            return Err(SnapshotError::Invalid(
+                "neighbour masks do not match the obstacles",
+            ));
+        }
+        Ok(pathing_grid)
+    }
+    /// Reads a snapshot to its end and loads the grid, see [from_snapshot](Self::from_snapshot).
+    pub fn read_snapshot<R: Read>(mut reader: R) -> Result<PathingGrid, SnapshotError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        PathingGrid::from_snapshot(&data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use grid_util::grid::Grid;
+
+    use super::*;
+    use crate::SearchConfig;
+
+    #[test]
+    fn round_trip() {
+        let mut pathing_grid: PathingGrid = "
+            ...#....
+            ##.#.##.
+            ...#..#.
+            .#..#.#.
+        "
+        .parse()
+        .unwrap();
+        pathing_grid.set_cost_model(CostModel::Octile);
+        let bytes = pathing_grid.to_snapshot();
+        let loaded = PathingGrid::read_snapshot(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.to_string(), pathing_grid.to_string());
+        assert_eq!(loaded.neighbours().values, pathing_grid.neighbours().values);
+        assert_eq!(loaded.transposed.values, pathing_grid.transposed.values);
+        assert_eq!(loaded.components(), pathing_grid.components());
+        assert_eq!(loaded.cost_model(), CostModel::Octile);
+        let (start, goal) = (Point::new(0, 0), Point::new(7, 3));
+        assert_eq!(
+            loaded.get_path_result_single_goal(start, goal, &SearchConfig::optimal()),
+            pathing_grid.get_path_result_single_goal(start, goal, &SearchConfig::optimal())
+        );
+        assert!(matches!(
+            PathingGrid::from_snapshot(&bytes[..bytes.len() - 1]),
+            Err(SnapshotError::Truncated)
+        ));
+        let mut miscounted = bytes.clone();
+        let last = miscounted.len() - 4;
+        miscounted[last] ^= 1;
+        assert!(matches!(
+            PathingGrid::from_snapshot(&miscounted),
+            Err(SnapshotError::Invalid(_))
+        ));
+        // The neighbour masks directly follow the header and the single obstacle word.
+        let mut tampered = bytes.clone();
+        tampered[HEADER_LEN + 8] ^= 1;
+        assert!(matches!(
+            PathingGrid::from_snapshot(&tampered),
+            Err(SnapshotError::Invalid(_))
+        ));
+        let mut future = bytes.clone();
+        future[4] = 2;
+        assert!(matches!(
+            PathingGrid::from_snapshot(&future),
+            Err(SnapshotError::UnsupportedVersion(2))
+        ));
+        assert!(matches!(
+            PathingGrid::from_snapshot(b"nope"),
+            Err(SnapshotError::BadMagic)
+        ));
+        assert_eq!(loaded.width(), 8);
+    }
+}
@@ -0,0 +1,315 @@
+//! Pathfinding on grids where free cells have individual traversal costs, for example to model
+//! terrain like roads, mud or shallow water. Since Jump Point Search relies on uniform costs, the
+//! search used here is a plain A* over the grid neighbourhood. With the admissible heuristic of
+//! [SearchConfig::optimal](crate::SearchConfig::optimal), the returned paths are optimal with
+//! respect to the cell costs.
+use grid_util::grid::{Grid, SimpleGrid};
+use grid_util::point::Point;
+use itertools::Itertools;
+use log::info;
+
+use crate::astar_jps::astar_jps_with_config;
+use crate::{
+    Connectivity, CostModel, DiagonalPolicy, PathError, PathResult, PathingGrid, SearchConfig,
+    SearchStats, OCTILE_DIAGONAL_COST,
+};
+
+/// Cost of a cell if none is set explicitly, matching the move cost used by [PathingGrid].
+pub const DEFAULT_COST: i32 = 1;
+/// The largest cost of a cell, for which the cost of any single move still fits in an [i32].
+pub const MAX_COST: i32 = i32::MAX / OCTILE_DIAGONAL_COST;
+
+/// [WeightedGrid] pairs a [PathingGrid], which keeps track of obstacles and components, with a
+/// traversal cost for every cell. Moving onto a cell costs the cost of that cell multiplied by the
+/// cost of the move under the [CostModel] of the [PathingGrid]. Searches sum these costs as
+/// [i64], so they cannot overflow, but the cost of a [PathResult] saturates at [i32::MAX].
+/// Implements [Grid] by building on [PathingGrid].
+#[derive(Clone, Debug)]
+pub struct WeightedGrid {
+    pathing_grid: PathingGrid,
+    costs: SimpleGrid<i32>,
+    /// Lower bound on the cost of any cell, used to keep the heuristic admissible.
+    min_cost: i32,
+}
+
+impl Default for WeightedGrid {
+    fn default() -> WeightedGrid {
+        WeightedGrid {
+            pathing_grid: PathingGrid::default(),
+            costs: SimpleGrid::default(),
+            min_cost: DEFAULT_COST,
+        }
+    }
+}
+
+impl WeightedGrid {
+    /// Retrieves the traversal cost of a cell.
+    pub fn get_cost(&self, x: usize, y: usize) -> i32 {
+        self.costs.get(x, y)
+    }
+    /// Sets the traversal cost of a cell.
+    ///
+    /// # Panics
+    /// Panics if the cost is negative or larger than [MAX_COST].
+    pub fn set_cost(&mut self, x: usize, y: usize, cost: i32) {
+        assert!(
+            (0..=MAX_COST).contains(&cost),
+            "cell costs must lie within 0..={}, got {}",
+            MAX_COST,
+            cost
+        );
+        self.costs.set(x, y, cost);
+        self.min_cost = self.min_cost.min(cost);
+    }
+    /// The underlying grid, which keeps track of obstacles and components. Cells are blocked using
+    /// [set](Grid::set) on the [WeightedGrid].
+    pub fn pathing_grid(&self) -> &PathingGrid {
+        &self.pathing_grid
+    }
+    /// Changes the [Connectivity] of the underlying grid, see [PathingGrid::set_connectivity].
+    pub fn set_connectivity(&mut self, connectivity: Connectivity) {
+        self.pathing_grid.set_connectivity(connectivity);
+    }
+    /// Changes the [DiagonalPolicy] of the underlying grid, see
+    /// [PathingGrid::set_diagonal_policy].
+    pub fn set_diagonal_policy(&mut self, diagonal_policy: DiagonalPolicy) {
+        self.pathing_grid.set_diagonal_policy(diagonal_policy);
+    }
+    /// Changes the [CostModel] of the underlying grid, see [PathingGrid::set_cost_model].
+    pub fn set_cost_model(&mut self, cost_model: CostModel) {
+        self.pathing_grid.set_cost_model(cost_model);
+    }
+    fn weighted_neighborhood(&self, pos: &Point) -> Vec<(Point, i64)> {
+        self.pathing_grid
+            .pathfinding_neighborhood(pos)
+            .into_iter()
+            .map(|(p, c)| (p, i64::from(self.costs.get_point(p)) * i64::from(c)))
+            .collect::<Vec<_>>()
+    }
+    /// Computes a minimum cost path from start to goal using A*. If the
+    /// [goal tolerance](SearchConfig::goal_tolerance) is positive, then it will path to a point
+    /// within that move distance of the goal, which is useful if goal itself is blocked. Paths are
+    /// only guaranteed to be of minimum cost if the
+    /// [heuristic weight](SearchConfig::heuristic_weight) is 1, as in [SearchConfig::optimal].
+    pub fn get_path_single_goal(
+        &self,
+        start: Point,
+        goal: Point,
+        config: &SearchConfig,
+    ) -> Result<Vec<Point>, PathError> {
+        self.get_path_result_single_goal(start, goal, config)
+            .map(|result| result.waypoints)
+    }
+    /// Like [get_path_single_goal](Self::get_path_single_goal), but returns a [PathResult] which
+    /// also holds the cost of the path and search statistics. Its waypoints are the full path.
+    pub fn get_path_result_single_goal(
+        &self,
+        start: Point,
+        goal: Point,
+        config: &SearchConfig,
+    ) -> Result<PathResult, PathError> {
+        self.pathing_grid.check_start(&start)?;
+        if self
+            .pathing_grid
+            .region_unreachable(&start, &goal, config.goal_tolerance)
+        {
+            info!("{} is not reachable from {}", goal, start);
+            return Err(self.pathing_grid.unreachable_error());
+        }
+        self.find_path(start, &[goal], config)
+    }
+    /// Computes a path from start to the cheapest reachable goal among the given goals, returning
+    /// the selected goal along with the path.
+    pub fn get_path_multiple_goals(
+        &self,
+        start: Point,
+        goals: Vec<&Point>,
+        config: &SearchConfig,
+    ) -> Result<(Point, Vec<Point>), PathError> {
+        self.get_path_result_multiple_goals(start, goals, config)
+            .map(|result| (result.goal, result.waypoints))
+    }
+    /// Like [get_path_multiple_goals](Self::get_path_multiple_goals), but returns a [PathResult]
+    /// which also holds the cost of the path and search statistics. Its waypoints are the full
+    /// path.
+    pub fn get_path_result_multiple_goals(
+        &self,
+        start: Point,
+        goals: Vec<&Point>,
+        config: &SearchConfig,
+    ) -> Result<PathResult, PathError> {
+        self.pathing_grid.check_start(&start)?;
+        if goals.is_empty() {
+            return Err(PathError::NoGoals);
+        }
+        let goals = goals
+            .into_iter()
+            .filter(|goal| {
+                !self
+                    .pathing_grid
+                    .region_unreachable(&start, goal, config.goal_tolerance)
+            })
+            .copied()
+            .collect_vec();
+        if goals.is_empty() {
+            info!("No goal is reachable from {}", start);
+            return Err(self.pathing_grid.unreachable_error());
+        }
+        self.find_path(start, &goals, config)
+    }
+    fn find_path(
+        &self,
+        start: Point,
+        goals: &[Point],
+        config: &SearchConfig,
+    ) -> Result<PathResult, PathError> {
+        let tolerance = config.goal_tolerance;
+        let mut stats = SearchStats::default();
+        let mut expanded = Vec::new();
+        let (mut path, cost) = astar_jps_with_config(
+            &start,
+            |_, node| {
+                if config.record_expanded {
+                    expanded.push(*node);
+                }
+                self.weighted_neighborhood(node)
+            },
+            |point| {
+                goals
+                    .iter()
+                    .map(|goal| {
+                        let distance = i64::from(
+                            self.pathing_grid
+                                .region_cost_distance(point, goal, tolerance),
+                        ) * i64::from(self.min_cost);
+                        (distance as f64 * config.heuristic_weight as f64) as i64
+                    })
+                    .min()
+                    .unwrap()
+            },
+            |node_pos| {
+                goals
+                    .iter()
+                    .any(|goal| self.pathing_grid.move_distance(node_pos, goal) <= tolerance)
+            },
+            config,
+            &mut stats,
+        )
+        .map(|(v, c)| (v.collect_vec(), c))
+        .ok_or_else(|| self.pathing_grid.search_failure(config, &stats))?;
+        path.reverse();
+        let last = path.last().unwrap();
+        let goal = *goals
+            .iter()
+            .find(|goal| self.pathing_grid.move_distance(last, goal) <= tolerance)
+            .unwrap();
+        Ok(PathResult {
+            waypoints: path,
+            cost: i32::try_from(cost).unwrap_or(i32::MAX),
+            goal,
+            stats,
+            expanded,
+        })
+    }
+    /// Regenerates the components if they are marked as dirty.
+    pub fn update(&mut self) {
+        self.pathing_grid.update();
+    }
+    /// Generates the components of the underlying [PathingGrid].
+    pub fn generate_components(&mut self) {
+        self.pathing_grid.generate_components();
+    }
+}
+
+impl Grid<bool> for WeightedGrid {
+    fn new(width: usize, height: usize, default_value: bool) -> Self {
+        WeightedGrid {
+            pathing_grid: PathingGrid::new(width, height, default_value),
+            costs: SimpleGrid::new(width, height, DEFAULT_COST),
+            min_cost: DEFAULT_COST,
+        }
+    }
+    fn get(&self, x: usize, y: usize) -> bool {
+        self.pathing_grid.get(x, y)
+    }
+    fn set(&mut self, x: usize, y: usize, blocked: bool) {
+        self.pathing_grid.set(x, y, blocked);
+    }
+    fn width(&self) -> usize {
+        self.pathing_grid.width()
+    }
+    fn height(&self) -> usize {
+        self.pathing_grid.height()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A straight path through expensive terrain is more costly than a detour over cheap terrain.
+    #[test]
+    fn avoids_expensive_cells() {
+        let mut weighted_grid = WeightedGrid::new(5, 3, false);
+        for y in 0..3 {
+            weighted_grid.set_cost(2, y, 10);
+        }
+        weighted_grid.set_cost(2, 2, 1);
+        weighted_grid.generate_components();
+        let path = weighted_grid
+            .get_path_single_goal(Point::new(0, 0), Point::new(4, 0), &SearchConfig::optimal())
+            .unwrap();
+        assert_eq!(path.first(), Some(&Point::new(0, 0)));
+        assert_eq!(path.last(), Some(&Point::new(4, 0)));
+        assert!(path.contains(&Point::new(2, 2)));
+        let result = weighted_grid
+            .get_path_result_single_goal(
+                Point::new(0, 0),
+                Point::new(4, 0),
+                &SearchConfig::optimal(),
+            )
+            .unwrap();
+        assert_eq!(result.cost, 4);
+    }
+
+    #[test]
+    fn selects_cheapest_goal() {
+        let mut weighted_grid = WeightedGrid::new(5, 1, false);
+        weighted_grid.set_cost(1, 0, 20);
+        weighted_grid.generate_components();
+        let near = Point::new(0, 0);
+        let far = Point::new(4, 0);
+        let start = Point::new(2, 0);
+        let (goal, path) = weighted_grid
+            .get_path_multiple_goals(start, vec![&near, &far], &SearchConfig::optimal())
+            .unwrap();
+        assert_eq!(goal, far);
+        assert_eq!(path, vec![start, Point::new(3, 0), far]);
+    }
+
+    /// Paths through cells of the largest cost are found without overflowing, and their cost
+    /// saturates.
+    #[test]
+    fn saturates_large_costs() {
+        let mut weighted_grid = WeightedGrid::new(4, 1, false);
+        weighted_grid.set_cost_model(CostModel::Octile);
+        for x in 0..4 {
+            weighted_grid.set_cost(x, 0, MAX_COST);
+        }
+        let result = weighted_grid
+            .get_path_result_single_goal(
+                Point::new(0, 0),
+                Point::new(3, 0),
+                &SearchConfig::optimal(),
+            )
+            .unwrap();
+        assert_eq!(result.waypoints.len(), 4);
+        assert_eq!(result.cost, i32::MAX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_costs_above_maximum() {
+        WeightedGrid::new(1, 1, false).set_cost(0, 0, MAX_COST + 1);
+    }
+}